@@ -1,10 +1,206 @@
-use clap::{Parser, Subcommand, ValueEnum};
-use log;
+use chrono::{DateTime, Utc};
+use clap::builder::PossibleValue;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use simple_logger::SimpleLogger;
 
+use crate::error::AdtError;
+use crate::export::{ExportOptions, OutputFormat, WriteMode};
+use crate::table::{Format, ReadOptions};
+use crate::utils::timestamp_from_str;
+
+impl ValueEnum for Format {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            Format::Parquet,
+            Format::Delta,
+            Format::Csv,
+            Format::Json,
+            Format::Arrow,
+            Format::Avro,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        let value = PossibleValue::new(self.name());
+        Some(match self {
+            Format::Json => value.help("newline delimited json"),
+            Format::Arrow => value.help("arrow ipc file (feather v2) or stream"),
+            _ => value,
+        })
+    }
+}
+
+/// options of the csv and json readers, shared by tbl and every --table
+#[derive(Args, Clone)]
+#[command(next_help_heading = "Read options (applied to every table)")]
+pub struct ReadArgs {
+    /// csv field delimiter (a single ascii character or \t), tab for .tsv
+    /// files and comma otherwise when omitted
+    #[arg(long, value_parser = parse_ascii_char)]
+    pub delimiter: Option<u8>,
+    /// csv files have no header line
+    #[arg(long, default_value_t = false)]
+    pub no_header: bool,
+    /// csv quote character
+    #[arg(long, value_parser = parse_ascii_char, default_value = "\"")]
+    pub quote: u8,
+    /// csv escape character
+    #[arg(long, value_parser = parse_ascii_char)]
+    pub escape: Option<u8>,
+    /// number of records read to infer the csv or json schema
+    #[arg(long, default_value_t = 1000)]
+    pub infer_sample_size: usize,
+    /// csv or json file schema, as col:type,... (skips inference)
+    #[arg(long)]
+    pub schema: Option<String>,
+}
+
+impl From<&ReadArgs> for ReadOptions {
+    fn from(args: &ReadArgs) -> Self {
+        Self {
+            delimiter: args.delimiter,
+            no_header: args.no_header,
+            quote: args.quote,
+            escape: args.escape,
+            infer_sample_size: args.infer_sample_size,
+            schema: args.schema.clone(),
+        }
+    }
+}
+
+fn parse_ascii_char(arg: &str) -> Result<u8, String> {
+    match arg {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ if arg.len() == 1 && arg.is_ascii() => Ok(arg.as_bytes()[0]),
+        _ => Err(format!(
+            "Invalid character '{}', expected a single ascii character",
+            arg
+        )),
+    }
+}
+
+/// rows shown by parquet-meta
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
-pub enum Format {
-    Parquet,
-    Delta,
+pub enum MetaLevel {
+    /// one row per file
+    Files,
+    /// one row per column chunk of each row group
+    RowGroups,
+    /// one row per column, merged over the row groups of every file
+    Columns,
+}
+
+impl ValueEnum for OutputFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            OutputFormat::Csv,
+            OutputFormat::Json,
+            OutputFormat::Parquet,
+            OutputFormat::Arrow,
+            OutputFormat::Delta,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        let value = PossibleValue::new(self.name());
+        Some(match self {
+            OutputFormat::Json => value.help("newline delimited json"),
+            OutputFormat::Arrow => value.help("arrow ipc file (feather v2)"),
+            _ => value,
+        })
+    }
+}
+
+impl ValueEnum for WriteMode {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            WriteMode::Append,
+            WriteMode::Overwrite,
+            WriteMode::ErrorIfExists,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(PossibleValue::new(self.name()))
+    }
+}
+
+/// options of the view exports
+#[derive(Args, Clone)]
+pub struct ExportArgs {
+    /// format of the output, guessed from the output path extension when omitted.
+    /// Delta tables on S3 are committed with AWS_S3_LOCKING_PROVIDER=dynamodb, or
+    /// AWS_S3_ALLOW_UNSAFE_RENAME=true when there is a single writer
+    #[arg(long, value_enum)]
+    pub output_format: Option<OutputFormat>,
+    /// write mode of a delta output
+    #[arg(long, value_enum, default_value_t = WriteMode::ErrorIfExists)]
+    pub mode: WriteMode,
+    /// columns to partition the output by (comma separated)
+    #[arg(long, value_delimiter = ',')]
+    pub partition_by: Vec<String>,
+    /// parquet and delta compression codec: uncompressed, snappy, lz4, gzip(level),
+    /// zstd(level) or brotli(level)
+    #[arg(long)]
+    pub compression: Option<String>,
+    /// maximum number of rows of parquet and delta row groups
+    #[arg(long)]
+    pub row_group_size: Option<usize>,
+    /// disable parquet and delta dictionary encoding
+    #[arg(long, default_value_t = false)]
+    pub no_dictionary: bool,
+}
+
+impl From<&ExportArgs> for ExportOptions {
+    fn from(args: &ExportArgs) -> Self {
+        Self {
+            output_format: args.output_format,
+            mode: args.mode,
+            partition_by: args.partition_by.clone(),
+            compression: args.compression.clone(),
+            row_group_size: args.row_group_size,
+            no_dictionary: args.no_dictionary,
+        }
+    }
+}
+
+/// additional table given as name=path[:format]
+#[derive(Clone)]
+pub struct TableArg {
+    pub name: String,
+    pub path: String,
+    pub format: Option<Format>,
+}
+
+fn parse_table_arg(arg: &str) -> Result<TableArg, String> {
+    let (name, location) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid table '{}', expected name=path[:format]", arg))?;
+    if name.is_empty() || location.is_empty() {
+        return Err(format!(
+            "Invalid table '{}', expected name=path[:format]",
+            arg
+        ));
+    }
+    // paths may contain ':' (s3://...), so only a known format suffix is split off
+    let (path, format) = match location.rsplit_once(':') {
+        Some((path, fmt)) => match Format::from_str(fmt, true) {
+            Ok(format) => (path, Some(format)),
+            Err(_) => (location, None),
+        },
+        None => (location, None),
+    };
+    Ok(TableArg {
+        name: name.to_string(),
+        path: path.to_string(),
+        format,
+    })
+}
+
+fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| format!("Invalid value '{}', expected name=value", arg))
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -18,6 +214,11 @@ pub enum LogLevel {
 #[derive(Parser)]
 #[command(name = "adt")]
 #[command(author, version, about, long_about = None)]
+#[command(
+    after_help = "Exit codes: 1 other failure, 2 usage, 3 invalid path, 4 unsupported type, \
+5 malformed column spec, 6 object store authentication, 7 object store, 8 delta log, \
+9 invalid table, 10 sql planning, 11 query execution"
+)]
 pub struct Cli {
     #[arg(short, long, value_enum, default_value_t = LogLevel::Info)]
     log_level: LogLevel,
@@ -29,47 +230,264 @@ pub struct Cli {
 pub enum Commands {
     /// view (and export) parquet or delta tables
     View {
-        table_path: String,
-        #[arg(short, long, value_enum, default_value_t = Format::Delta)]
-        format: Format,
-        #[arg(short, long, default_value_t = String::from("select * from tbl"))]
-        query: String,
-        #[arg(short, long, default_value_t = 50)]
-        limit: usize,
+        /// table registered as tbl
+        #[arg(required_unless_present = "tables")]
+        table_path: Option<String>,
+        /// format of tbl, detected from its path when omitted
+        #[arg(short, long, value_enum)]
+        format: Option<Format>,
+        /// query to run, every row of the first table by default
+        #[arg(short, long)]
+        query: Option<String>,
+        /// rows of select queries: all of them are paged in the viewer, 50 are
+        /// printed or exported by default
+        #[arg(short, long)]
+        limit: Option<usize>,
         #[arg(short, long)]
         partitions: Option<String>,
+        /// additional table to register, as name=path[:format] (repeatable)
+        #[arg(short = 't', long = "table", value_parser = parse_table_arg)]
+        tables: Vec<TableArg>,
+        /// partitions of an additional table, as name=col:type,... (repeatable)
+        #[arg(long = "table-partitions", value_parser = parse_key_value)]
+        table_partitions: Vec<(String, String)>,
+        #[command(flatten)]
+        read_options: ReadArgs,
         #[arg(long, default_value_t = false)]
         no_tui: bool,
         #[arg(short, long)]
         output_path: Option<String>,
+        #[command(flatten)]
+        export_options: ExportArgs,
+        /// version of the tbl delta table to load
+        #[arg(long, conflicts_with = "as_of")]
+        version: Option<i64>,
+        /// load the tbl delta table as it was at this timestamp (UTC)
+        #[arg(long, value_parser = timestamp_from_str)]
+        as_of: Option<DateTime<Utc>>,
+    },
+    /// interactive sql shell
+    Shell {
+        /// table to register, as name=path[:format] (repeatable)
+        #[arg(short = 't', long = "table", value_parser = parse_table_arg)]
+        tables: Vec<TableArg>,
+        /// partitions of a table, as name=col:type,... (repeatable)
+        #[arg(long = "table-partitions", value_parser = parse_key_value)]
+        table_partitions: Vec<(String, String)>,
+        #[command(flatten)]
+        read_options: ReadArgs,
+    },
+    /// serve tables through a REST API
+    Serve {
+        /// table registered as tbl
+        #[arg(required_unless_present = "tables")]
+        table_path: Option<String>,
+        /// format of tbl, detected from its path when omitted
+        #[arg(short, long, value_enum)]
+        format: Option<Format>,
+        #[arg(short, long)]
+        partitions: Option<String>,
+        /// additional table to register, as name=path[:format] (repeatable)
+        #[arg(short = 't', long = "table", value_parser = parse_table_arg)]
+        tables: Vec<TableArg>,
+        /// partitions of an additional table, as name=col:type,... (repeatable)
+        #[arg(long = "table-partitions", value_parser = parse_key_value)]
+        table_partitions: Vec<(String, String)>,
+        #[command(flatten)]
+        read_options: ReadArgs,
+        #[arg(long, default_value_t = String::from("127.0.0.1"))]
+        host: String,
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// serve tables through arrow flight sql
+    FlightServe {
+        /// table registered as tbl
+        #[arg(required_unless_present = "tables")]
+        table_path: Option<String>,
+        /// format of tbl, detected from its path when omitted
+        #[arg(short, long, value_enum)]
+        format: Option<Format>,
+        #[arg(short, long)]
+        partitions: Option<String>,
+        /// additional table to register, as name=path[:format] (repeatable)
+        #[arg(short = 't', long = "table", value_parser = parse_table_arg)]
+        tables: Vec<TableArg>,
+        /// partitions of an additional table, as name=col:type,... (repeatable)
+        #[arg(long = "table-partitions", value_parser = parse_key_value)]
+        table_partitions: Vec<(String, String)>,
+        #[command(flatten)]
+        read_options: ReadArgs,
+        #[arg(long, default_value_t = String::from("127.0.0.1"))]
+        host: String,
+        #[arg(long, default_value_t = 50051)]
+        port: u16,
+    },
+    /// serve tables through the postgres wire protocol
+    PgServe {
+        /// table registered as tbl
+        #[arg(required_unless_present = "tables")]
+        table_path: Option<String>,
+        /// format of tbl, detected from its path when omitted
+        #[arg(short, long, value_enum)]
+        format: Option<Format>,
+        #[arg(short, long)]
+        partitions: Option<String>,
+        /// additional table to register, as name=path[:format] (repeatable)
+        #[arg(short = 't', long = "table", value_parser = parse_table_arg)]
+        tables: Vec<TableArg>,
+        /// partitions of an additional table, as name=col:type,... (repeatable)
+        #[arg(long = "table-partitions", value_parser = parse_key_value)]
+        table_partitions: Vec<(String, String)>,
+        #[command(flatten)]
+        read_options: ReadArgs,
+        #[arg(long, default_value_t = String::from("127.0.0.1"))]
+        host: String,
+        #[arg(long, default_value_t = 5432)]
+        port: u16,
+    },
+    /// show delta table commit history
+    History {
+        table_path: String,
+        /// maximum number of commits to show, newest first
+        #[arg(short, long)]
+        limit: Option<usize>,
+        /// first version to show (inclusive)
+        #[arg(long)]
+        from_version: Option<i64>,
+        /// last version to show (inclusive)
+        #[arg(long)]
+        to_version: Option<i64>,
+        #[arg(long, default_value_t = false)]
+        no_tui: bool,
+        /// print commits as newline delimited json (implies --no-tui)
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// profile the columns of a table: counts, min and max, distributions and
+    /// most frequent values, computed in three scans of the table
+    Profile {
+        /// table registered as tbl
+        table_path: String,
+        /// format of tbl, detected from its path when omitted
+        #[arg(short, long, value_enum)]
+        format: Option<Format>,
+        #[arg(short, long)]
+        partitions: Option<String>,
+        #[command(flatten)]
+        read_options: ReadArgs,
+        /// count the distinct values exactly instead of estimating them
+        #[arg(long, default_value_t = false)]
+        exact: bool,
+        /// number of most frequent values of each column
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+        /// number of histogram buckets of numeric values and string lengths
+        #[arg(long, default_value_t = 10)]
+        bins: usize,
+        /// answer from the parquet footers or delta log without scanning the
+        /// table: row and null counts, min and max only
+        #[arg(long, default_value_t = false)]
+        stats: bool,
+        /// version of the tbl delta table to load
+        #[arg(long, conflicts_with = "as_of")]
+        version: Option<i64>,
+        /// load the tbl delta table as it was at this timestamp (UTC)
+        #[arg(long, value_parser = timestamp_from_str)]
+        as_of: Option<DateTime<Utc>>,
+        #[arg(long, default_value_t = false)]
+        no_tui: bool,
+        /// print column profiles as newline delimited json (implies --no-tui)
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// show the row groups, column chunks, statistics and footer metadata of
+    /// a parquet file, of a directory or of the active files of a delta table
+    ParquetMeta {
+        /// parquet file, directory or delta table
+        path: String,
+        /// rows shown: one per file, per row group column chunk or per column
+        #[arg(long, value_enum, default_value_t = MetaLevel::Columns)]
+        level: MetaLevel,
+        #[arg(long, default_value_t = false)]
+        no_tui: bool,
+        /// print rows as newline delimited json (implies --no-tui)
+        #[arg(long, default_value_t = false)]
+        json: bool,
     },
     /// execute sql file
     Execute { sql_file: String },
     /// print parquet or delta table schema
     Schema {
-        table_path: String,
-        #[arg(short, long, value_enum, default_value_t = Format::Delta)]
-        format: Format,
+        /// table registered as tbl
+        #[arg(required_unless_present = "tables")]
+        table_path: Option<String>,
+        /// format of tbl, detected from its path when omitted
+        #[arg(short, long, value_enum)]
+        format: Option<Format>,
         #[arg(short, long)]
         partitions: Option<String>,
+        /// additional table to register, as name=path[:format] (repeatable)
+        #[arg(short = 't', long = "table", value_parser = parse_table_arg)]
+        tables: Vec<TableArg>,
+        /// partitions of an additional table, as name=col:type,... (repeatable)
+        #[arg(long = "table-partitions", value_parser = parse_key_value)]
+        table_partitions: Vec<(String, String)>,
+        #[command(flatten)]
+        read_options: ReadArgs,
         #[arg(long, default_value_t = false)]
         no_tui: bool,
+        /// version of the tbl delta table to load
+        #[arg(long, conflicts_with = "as_of")]
+        version: Option<i64>,
+        /// load the tbl delta table as it was at this timestamp (UTC)
+        #[arg(long, value_parser = timestamp_from_str)]
+        as_of: Option<DateTime<Utc>>,
     },
     /// Print logical plan
     Explain {
-        table_path: String,
-        #[arg(short, long, value_enum, default_value_t = Format::Delta)]
-        format: Format,
-        #[arg(short, long, default_value_t = String::from("select * from tbl"))]
-        query: String,
+        /// table registered as tbl
+        #[arg(required_unless_present = "tables")]
+        table_path: Option<String>,
+        /// format of tbl, detected from its path when omitted
+        #[arg(short, long, value_enum)]
+        format: Option<Format>,
+        /// query to run, every row of the first table by default
+        #[arg(short, long)]
+        query: Option<String>,
         #[arg(short, long, default_value_t = 50)]
         limit: usize,
         #[arg(short, long)]
         partitions: Option<String>,
+        /// additional table to register, as name=path[:format] (repeatable)
+        #[arg(short = 't', long = "table", value_parser = parse_table_arg)]
+        tables: Vec<TableArg>,
+        /// partitions of an additional table, as name=col:type,... (repeatable)
+        #[arg(long = "table-partitions", value_parser = parse_key_value)]
+        table_partitions: Vec<(String, String)>,
+        #[command(flatten)]
+        read_options: ReadArgs,
+        /// version of the tbl delta table to load
+        #[arg(long, conflicts_with = "as_of")]
+        version: Option<i64>,
+        /// load the tbl delta table as it was at this timestamp (UTC)
+        #[arg(long, value_parser = timestamp_from_str)]
+        as_of: Option<DateTime<Utc>>,
     },
 }
 
 impl Cli {
+    /// Log at the --log-level, unless it is off
+    pub fn init_logger(&self) -> Result<(), AdtError> {
+        if let Some(level) = self.get_log_level() {
+            SimpleLogger::new()
+                .with_level(level)
+                .init()
+                .map_err(|e| AdtError::Logger(e.to_string()))?;
+        }
+        Ok(())
+    }
+
     pub fn get_log_level(&self) -> Option<log::LevelFilter> {
         match self.log_level {
             LogLevel::Off => None,
@@ -78,3 +496,34 @@ impl Cli {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_arg_with_format() {
+        let table = parse_table_arg("t=s3://bucket/dir:csv").unwrap();
+        assert_eq!(table.name, "t");
+        assert_eq!(table.path, "s3://bucket/dir");
+        assert_eq!(table.format, Some(Format::Csv));
+    }
+
+    #[test]
+    fn table_arg_path_with_colons() {
+        // only a known format is split off the path
+        let table = parse_table_arg("t=s3://bucket/a:b").unwrap();
+        assert_eq!(table.path, "s3://bucket/a:b");
+        assert_eq!(table.format, None);
+        let table = parse_table_arg("t=/data/x").unwrap();
+        assert_eq!(table.path, "/data/x");
+        assert_eq!(table.format, None);
+    }
+
+    #[test]
+    fn table_arg_malformed() {
+        assert!(parse_table_arg("/data/x").is_err());
+        assert!(parse_table_arg("=/data/x").is_err());
+        assert!(parse_table_arg("t=").is_err());
+    }
+}
@@ -0,0 +1,472 @@
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+use std::time::Instant;
+
+use arrow::util::pretty::pretty_format_batches;
+use deltalake::DeltaVersion;
+use log::info;
+
+use crate::cli::{Cli, Commands, MetaLevel, ReadArgs, TableArg};
+use crate::context::SQLContext;
+use crate::error::{AdtError, Result};
+use crate::export::{ExportOptions, StreamExport};
+use crate::profile::{self, ProfileOptions};
+use crate::table::{Format, ReadOptions, TableContext, TableSpec};
+use crate::utils::{delta_version, ensure_scheme};
+use crate::view::{self, Display};
+use crate::{flight, history, parquet_meta, postgres, server, shell, tui};
+
+/// Table specs of a session: the positional table as tbl plus the --table ones
+fn table_specs(
+    table_path: &Option<String>,
+    format: Option<Format>,
+    partitions: &Option<String>,
+    version: DeltaVersion,
+    tables: &[TableArg],
+    table_partitions: &[(String, String)],
+    read_options: &ReadArgs,
+) -> Result<Vec<TableSpec>, AdtError> {
+    let read_options = ReadOptions::from(read_options);
+    if let Some((name, spec)) = table_partitions
+        .iter()
+        .find(|(name, _)| tables.iter().all(|table| table.name != *name))
+    {
+        return Err(AdtError::MalformedSpec {
+            spec: format!("{}={}", name, spec),
+            reason: format!("no --table named {}", name),
+        });
+    }
+    let mut specs = Vec::new();
+    if let Some(path) = table_path {
+        let spec = TableSpec::new("tbl", path)?.with_version(version);
+        specs.push(table_spec(
+            spec,
+            format,
+            partitions.as_deref(),
+            &read_options,
+        )?);
+    }
+    for table in tables {
+        let parts = table_partitions
+            .iter()
+            .find(|(name, _)| *name == table.name)
+            .map(|(_, spec)| spec.as_str());
+        let spec = TableSpec::new(&table.name, &table.path)?;
+        specs.push(table_spec(
+            spec,
+            table.format.or(format),
+            parts,
+            &read_options,
+        )?);
+    }
+    Ok(specs)
+}
+
+fn table_spec(
+    spec: TableSpec,
+    format: Option<Format>,
+    partitions: Option<&str>,
+    read_options: &ReadOptions,
+) -> Result<TableSpec, AdtError> {
+    let spec = match format {
+        Some(fmt) => spec.with_format(fmt),
+        None => spec,
+    };
+    let spec = match partitions {
+        Some(partitions) => spec.with_partitions(partitions)?,
+        None => spec,
+    };
+    Ok(spec.with_read_options(read_options.clone()))
+}
+
+/// Session over `specs` once its tables are registered
+async fn register(specs: Vec<TableSpec>) -> Result<Arc<TableContext>> {
+    let tblctx = TableContext::new(specs);
+    let req_time = Instant::now();
+    tblctx.register_tables().await?;
+    let req_time_elapsed = req_time.elapsed();
+    info!("Table registration time: {:.2?}", req_time_elapsed);
+    Ok(Arc::new(tblctx))
+}
+
+/// Run the command parsed from the command line
+pub async fn run(cli: &Cli) -> Result<()> {
+    match &cli.command {
+        Commands::View {
+            table_path,
+            format,
+            query,
+            partitions,
+            limit,
+            no_tui,
+            output_path,
+            export_options,
+            version,
+            as_of,
+            tables,
+            table_partitions,
+            read_options,
+        } => {
+            let tblctx = register(table_specs(
+                table_path,
+                *format,
+                partitions,
+                delta_version(*version, *as_of),
+                tables,
+                table_partitions,
+                read_options,
+            )?)
+            .await?;
+            // the viewer fetches the rows as they are scrolled to
+            let limit = limit.or((*no_tui || output_path.is_some()).then_some(50));
+            let query = query.clone().unwrap_or_else(|| tblctx.default_query());
+            let df = tblctx.exec_query(query.clone(), limit).await?;
+            let schema = Arc::new(df.schema().as_arrow().clone());
+            let stream = df.execute_stream().await.map_err(AdtError::from)?;
+            let export = match output_path {
+                Some(op) => {
+                    let output_url = ensure_scheme(op)?;
+                    tblctx.register_object_store(&output_url)?;
+                    Some(StreamExport::start(
+                        tblctx.context(),
+                        schema.clone(),
+                        output_url.as_str(),
+                        &ExportOptions::from(export_options),
+                    )?)
+                }
+                None => None,
+            };
+            if *no_tui {
+                view::drain_results(stream, Display::Print, export).await?;
+            } else {
+                let (sender, receiver) = tokio::sync::mpsc::channel(2);
+                let results =
+                    tokio::spawn(view::drain_results(stream, Display::Viewer(sender), export));
+                let shown = tokio::task::block_in_place(|| {
+                    let fetch_all = output_path.is_some();
+                    tui::show_query_in_tui(
+                        tblctx.clone(),
+                        &query,
+                        limit,
+                        schema,
+                        receiver,
+                        fetch_all,
+                    )
+                });
+                results.await??;
+                shown?;
+            }
+        }
+        Commands::Schema {
+            table_path,
+            partitions,
+            format,
+            no_tui,
+            version,
+            as_of,
+            tables,
+            table_partitions,
+            read_options,
+        } => {
+            let tblctx = register(table_specs(
+                table_path,
+                *format,
+                partitions,
+                delta_version(*version, *as_of),
+                tables,
+                table_partitions,
+                read_options,
+            )?)
+            .await?;
+            let req_time = Instant::now();
+            let records = tblctx
+                .schema()
+                .await?
+                .collect()
+                .await
+                .map_err(AdtError::from)?;
+            let req_time_elapsed = req_time.elapsed();
+            info!("Query execution time: {:.2?}", req_time_elapsed);
+            if *no_tui {
+                println!("{}", pretty_format_batches(&records)?);
+            } else {
+                tui::show_in_tui(records)?;
+            }
+        }
+        Commands::Explain {
+            table_path,
+            format,
+            query,
+            limit,
+            partitions,
+            version,
+            as_of,
+            tables,
+            table_partitions,
+            read_options,
+        } => {
+            let tblctx = register(table_specs(
+                table_path,
+                *format,
+                partitions,
+                delta_version(*version, *as_of),
+                tables,
+                table_partitions,
+                read_options,
+            )?)
+            .await?;
+            // parse the SQL
+            let query = query.clone().unwrap_or_else(|| tblctx.default_query());
+            let full_query = tblctx.build_query(query, Some(*limit));
+            let initial_plan = tblctx
+                .context()
+                .state()
+                .create_logical_plan(full_query.as_ref())
+                .await
+                .map_err(AdtError::from)?;
+            // show the plan
+            println!("Initial Plan:\n{:?}", initial_plan.clone());
+
+            let optimized_plan = tblctx
+                .context()
+                .state()
+                .optimize(&initial_plan)
+                .map_err(AdtError::from)?;
+
+            // show the plan
+            println!("Optimized Plan:\n{:?}", optimized_plan);
+        }
+        Commands::Serve {
+            table_path,
+            format,
+            partitions,
+            tables,
+            table_partitions,
+            read_options,
+            host,
+            port,
+        } => {
+            let tblctx = register(table_specs(
+                table_path,
+                *format,
+                partitions,
+                DeltaVersion::Newest,
+                tables,
+                table_partitions,
+                read_options,
+            )?)
+            .await?;
+            server::serve(tblctx, host, *port).await?;
+        }
+        Commands::FlightServe {
+            table_path,
+            format,
+            partitions,
+            tables,
+            table_partitions,
+            read_options,
+            host,
+            port,
+        } => {
+            let tblctx = register(table_specs(
+                table_path,
+                *format,
+                partitions,
+                DeltaVersion::Newest,
+                tables,
+                table_partitions,
+                read_options,
+            )?)
+            .await?;
+            flight::serve(tblctx, host, *port).await?;
+        }
+        Commands::PgServe {
+            table_path,
+            format,
+            partitions,
+            tables,
+            table_partitions,
+            read_options,
+            host,
+            port,
+        } => {
+            let tblctx = register(table_specs(
+                table_path,
+                *format,
+                partitions,
+                DeltaVersion::Newest,
+                tables,
+                table_partitions,
+                read_options,
+            )?)
+            .await?;
+            postgres::serve(tblctx, host, *port).await?;
+        }
+        Commands::Shell {
+            tables,
+            table_partitions,
+            read_options,
+        } => {
+            let mut shell = shell::Shell::new()?;
+            shell
+                .register_tables(table_specs(
+                    &None,
+                    None,
+                    &None,
+                    DeltaVersion::Newest,
+                    tables,
+                    table_partitions,
+                    read_options,
+                )?)
+                .await?;
+            shell.run().await?;
+        }
+        Commands::History {
+            table_path,
+            limit,
+            from_version,
+            to_version,
+            no_tui,
+            json,
+        } => {
+            let req_time = Instant::now();
+            let entries =
+                history::commit_history(table_path, *from_version, *to_version, *limit).await?;
+            let req_time_elapsed = req_time.elapsed();
+            info!("History read time: {:.2?}", req_time_elapsed);
+            if *json {
+                for entry in entries {
+                    println!("{}", entry.to_json()?);
+                }
+            } else {
+                let records = vec![history::history_to_batch(&entries)?];
+                if *no_tui {
+                    println!("{}", pretty_format_batches(&records)?);
+                } else {
+                    tui::show_in_tui(records)?;
+                }
+            }
+        }
+        Commands::Profile {
+            table_path,
+            format,
+            partitions,
+            read_options,
+            exact,
+            top,
+            bins,
+            stats,
+            version,
+            as_of,
+            no_tui,
+            json,
+        } => {
+            let tblctx = register(table_specs(
+                &Some(table_path.clone()),
+                *format,
+                partitions,
+                delta_version(*version, *as_of),
+                &[],
+                &[],
+                read_options,
+            )?)
+            .await?;
+            let req_time = Instant::now();
+            let profiles = if *stats {
+                profile::profile_from_statistics(&tblctx, "tbl").await?
+            } else {
+                let options = ProfileOptions {
+                    exact_distinct: *exact,
+                    top: *top,
+                    bins: *bins,
+                };
+                profile::profile_table(&tblctx, "tbl", &options).await?
+            };
+            let req_time_elapsed = req_time.elapsed();
+            info!("Profile time: {:.2?}", req_time_elapsed);
+            if *json {
+                for column in profiles {
+                    println!("{}", column.to_json());
+                }
+            } else {
+                let records = vec![profile::profiles_to_batch(&profiles)?];
+                if *no_tui {
+                    println!("{}", pretty_format_batches(&records)?);
+                } else {
+                    tui::show_in_tui(records)?;
+                }
+            }
+        }
+        Commands::ParquetMeta {
+            path,
+            level,
+            no_tui,
+            json,
+        } => {
+            let req_time = Instant::now();
+            let files = parquet_meta::read_metadata(path).await?;
+            let req_time_elapsed = req_time.elapsed();
+            info!("Footers read time: {:.2?}", req_time_elapsed);
+            let batch = match level {
+                MetaLevel::Files => parquet_meta::files_to_batch(&files)?,
+                MetaLevel::RowGroups => parquet_meta::row_groups_to_batch(&files)?,
+                MetaLevel::Columns => parquet_meta::columns_to_batch(&files)?,
+            };
+            if *json {
+                let mut writer = arrow::json::WriterBuilder::new()
+                    .with_explicit_nulls(true)
+                    .build::<_, arrow::json::writer::LineDelimited>(std::io::stdout());
+                writer.write(&batch)?;
+                writer.finish()?;
+            } else {
+                let records = vec![batch];
+                if *no_tui {
+                    println!("{}", pretty_format_batches(&records)?);
+                } else {
+                    tui::show_in_tui(records)?;
+                }
+            }
+        }
+        Commands::Execute { sql_file } => {
+            let ctx = SQLContext::new()?;
+            let mut query = "".to_owned();
+            let file = fs::File::open(sql_file).map_err(|e| AdtError::InvalidPath {
+                path: sql_file.clone(),
+                reason: e.to_string(),
+            })?;
+            let reader = BufReader::new(file);
+            for line in reader.lines() {
+                match line {
+                    Ok(line) if line.starts_with("--") => {
+                        continue;
+                    }
+                    Ok(line) => {
+                        let line = line.trim_end();
+                        query.push_str(line);
+                        if line.ends_with(';') {
+                            let df = ctx.sql(&query).await?;
+                            let records = df.collect().await.map_err(AdtError::from)?;
+                            println!("{}", pretty_format_batches(&records)?);
+                            query = "".to_string();
+                        } else {
+                            query.push('\n');
+                        }
+                    }
+                    _ => {
+                        break;
+                    }
+                }
+            }
+
+            // run the left over query if the last statement doesn't contain ‘;’
+            // ignore if it only consists of '\n'
+            if query.contains(|c| c != '\n') {
+                let df = ctx.sql(&query).await?;
+                let records = df.collect().await.map_err(AdtError::from)?;
+                println!("{}", pretty_format_batches(&records)?);
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,66 +1,72 @@
-use anyhow::Result;
 use datafusion::logical_expr::{DdlStatement, LogicalPlan};
 use datafusion::prelude::*;
 use deltalake::datafusion::execution::context::{SessionContext, SessionState};
 use deltalake::datafusion::execution::runtime_env::{RuntimeConfig, RuntimeEnv};
 use deltalake::datafusion::prelude::SessionConfig;
 use deltalake::delta_datafusion::DeltaTableFactory;
-use object_store;
+use log::debug;
 use object_store::aws::AmazonS3Builder;
 use std::sync::Arc;
 use url::Url;
 
+use crate::error::{AdtError, Result};
 use crate::utils::ensure_scheme;
+
+/// Session where tables are created with `CREATE EXTERNAL TABLE ... STORED AS
+/// DELTA|PARQUET|CSV|JSON|ARROW|AVRO LOCATION '...'` statements
 pub struct SQLContext {
     ctx: SessionContext,
 }
 
 impl SQLContext {
-    pub fn new() -> Self {
+    pub fn new() -> Result<Self> {
         let cfg = RuntimeConfig::new();
-        let env = RuntimeEnv::new(cfg).unwrap();
+        let env = RuntimeEnv::new(cfg)?;
         let ses = SessionConfig::new().with_information_schema(true);
         let mut state = SessionState::new_with_config_rt(ses, Arc::new(env));
         state
             .table_factories_mut()
             .insert("DELTA".to_string(), Arc::new(DeltaTableFactory {}));
-        Self {
+        Ok(Self {
             ctx: SessionContext::new_with_state(state),
-        }
+        })
+    }
+
+    pub fn context(&self) -> &SessionContext {
+        &self.ctx
     }
 
-    async fn register_object_store(&self, location: &String, file_type: &String) -> Result<()> {
-        let url = ensure_scheme(location).unwrap();
-        match (url.scheme(), file_type.as_str()) {
-            ("s3", ft) => {
-                let s3 = AmazonS3Builder::from_env()
-                    .with_bucket_name(
-                        url.host_str()
-                            .expect("failed to extract host/bucket from path"),
-                    )
-                    .build()
-                    .expect("Unable to create S3 object store");
-                let s3_url =
-                    Url::parse(&url[url::Position::BeforeScheme..url::Position::AfterHost])
-                        .expect("Unable to get bucket based S3 url");
-                let _ = self
-                    .ctx
-                    .runtime_env()
-                    .object_store_registry
-                    .register_store(&s3_url, Arc::new(s3));
-                match ft {
-                    "DELTA" => deltalake::aws::register_handlers(None),
-                    _ => (),
-                }
+    async fn register_object_store(&self, location: &str, file_type: &str) -> Result<()> {
+        let url = ensure_scheme(location)?;
+        if url.scheme() == "s3" {
+            let bucket = url.host_str().ok_or_else(|| AdtError::InvalidPath {
+                path: location.to_string(),
+                reason: "no bucket name".to_string(),
+            })?;
+            let s3 = AmazonS3Builder::from_env()
+                .with_bucket_name(bucket)
+                .build()?;
+            let s3_url = Url::parse(&url[url::Position::BeforeScheme..url::Position::AfterHost])
+                .map_err(|e| AdtError::InvalidPath {
+                    path: location.to_string(),
+                    reason: e.to_string(),
+                })?;
+            let _ = self
+                .ctx
+                .runtime_env()
+                .object_store_registry
+                .register_store(&s3_url, Arc::new(s3));
+            if file_type == "DELTA" {
+                deltalake::aws::register_handlers(None);
             }
-            _ => (),
         }
         Ok(())
     }
 
+    /// Run a plan, registering the object store of created external tables
     pub async fn execute_logical_plan(&self, plan: LogicalPlan) -> Result<DataFrame> {
         if let LogicalPlan::Ddl(DdlStatement::CreateExternalTable(cmd)) = &plan {
-            println!("file type: {:?}", cmd.file_type);
+            debug!("file type: {:?}", cmd.file_type);
             self.register_object_store(&cmd.location, &cmd.file_type)
                 .await?;
         }
@@ -68,10 +74,13 @@ impl SQLContext {
         Ok(df)
     }
 
+    /// Plan and run a sql statement
     pub async fn sql(&self, sql: &str) -> Result<DataFrame> {
         self.sql_with_options(sql, SQLOptions::new()).await
     }
 
+    /// Plan and run a sql statement, restricted to the ones allowed by
+    /// `options`
     pub async fn sql_with_options(&self, sql: &str, options: SQLOptions) -> Result<DataFrame> {
         let plan = self.ctx.state().create_logical_plan(sql).await?;
         options.verify_plan(&plan)?;
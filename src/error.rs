@@ -0,0 +1,319 @@
+use datafusion::arrow::error::ArrowError;
+use datafusion::error::DataFusionError;
+use deltalake::DeltaTableError;
+use thiserror::Error;
+
+pub type Result<T, E = AdtError> = std::result::Result<T, E>;
+
+/// Failures of adt, each one mapped to its own process exit code (2 is left to
+/// clap usage errors)
+#[derive(Debug, Error)]
+pub enum AdtError {
+    #[error("Invalid path {path}: {reason}")]
+    InvalidPath { path: String, reason: String },
+    #[error("Unsupported type '{0}'")]
+    UnsupportedType(String),
+    #[error("Malformed column spec '{spec}': {reason}")]
+    MalformedSpec { spec: String, reason: String },
+    #[error("Object store authentication fails: {0}")]
+    ObjectStoreAuth(String),
+    #[error("{0}")]
+    ObjectStore(String),
+    #[error("{0}")]
+    DeltaLog(String),
+    #[error("{0}")]
+    Table(String),
+    #[error("{0}")]
+    Planning(DataFusionError),
+    #[error("{0}")]
+    Execution(DataFusionError),
+    #[error("Logger initialization fails: {0}")]
+    Logger(String),
+    #[error("{0}")]
+    Export(String),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Server(String),
+    #[error("{0}")]
+    Terminal(String),
+}
+
+impl AdtError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AdtError::InvalidPath { .. } => 3,
+            AdtError::UnsupportedType(_) => 4,
+            AdtError::MalformedSpec { .. } => 5,
+            AdtError::ObjectStoreAuth(_) => 6,
+            AdtError::ObjectStore(_) => 7,
+            AdtError::DeltaLog(_) => 8,
+            AdtError::Table(_) => 9,
+            AdtError::Planning(_) => 10,
+            AdtError::Execution(_) => 11,
+            AdtError::Logger(_) => 1,
+            AdtError::Export(_) => 12,
+            AdtError::Io(_) => 13,
+            AdtError::Server(_) => 14,
+            AdtError::Terminal(_) => 15,
+        }
+    }
+
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            AdtError::InvalidPath { .. } => {
+                Some("paths are given as a local path or an s3://bucket/prefix url")
+            }
+            AdtError::UnsupportedType(_) => Some(
+                "supported types are int, bigint, float, double, string, date, timestamp \
+                and timestamp_ms",
+            ),
+            AdtError::MalformedSpec { .. } => {
+                Some("columns are given as name:type,... e.g. year:int,month:int")
+            }
+            AdtError::ObjectStoreAuth(_) => Some(
+                "check the AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY, AWS_SESSION_TOKEN and \
+                AWS_REGION environment variables",
+            ),
+            AdtError::ObjectStore(_) => {
+                Some("check the path exists and the store is reachable (AWS_ENDPOINT)")
+            }
+            AdtError::DeltaLog(_) => Some(
+                "check the path is a delta table and the requested version is still in its log",
+            ),
+            AdtError::Table(_) => None,
+            AdtError::Planning(_) => {
+                Some("check the query syntax and the table and column names (adt schema)")
+            }
+            AdtError::Export(_) => {
+                Some("the output format is taken from the path extension unless --output-format is given")
+            }
+            AdtError::Server(_) => Some("check the host and port are free and reachable (--host, --port)"),
+            AdtError::Execution(_)
+            | AdtError::Logger(_)
+            | AdtError::Io(_)
+            | AdtError::Terminal(_) => None,
+        }
+    }
+}
+
+impl From<DataFusionError> for AdtError {
+    fn from(err: DataFusionError) -> Self {
+        match err.find_root() {
+            DataFusionError::SQL(..)
+            | DataFusionError::Plan(_)
+            | DataFusionError::SchemaError(..) => AdtError::Planning(err),
+            DataFusionError::ObjectStore(store_err) if is_auth_failure(store_err) => {
+                AdtError::ObjectStoreAuth(err.to_string())
+            }
+            DataFusionError::ObjectStore(_) => AdtError::ObjectStore(err.to_string()),
+            _ => AdtError::Execution(err),
+        }
+    }
+}
+
+impl From<object_store::Error> for AdtError {
+    fn from(err: object_store::Error) -> Self {
+        if is_auth_failure(&err) {
+            AdtError::ObjectStoreAuth(err.to_string())
+        } else {
+            AdtError::ObjectStore(err.to_string())
+        }
+    }
+}
+
+impl From<DeltaTableError> for AdtError {
+    fn from(err: DeltaTableError) -> Self {
+        match err {
+            DeltaTableError::ObjectStore { source } => source.into(),
+            err => AdtError::DeltaLog(err.to_string()),
+        }
+    }
+}
+
+impl From<ArrowError> for AdtError {
+    fn from(err: ArrowError) -> Self {
+        AdtError::Execution(DataFusionError::ArrowError(err, None))
+    }
+}
+
+impl From<tokio::task::JoinError> for AdtError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        AdtError::Execution(DataFusionError::External(Box::new(err)))
+    }
+}
+
+impl From<rustyline::error::ReadlineError> for AdtError {
+    fn from(err: rustyline::error::ReadlineError) -> Self {
+        match err {
+            rustyline::error::ReadlineError::Io(err) => AdtError::Io(err),
+            err => AdtError::Terminal(err.to_string()),
+        }
+    }
+}
+
+impl From<tonic::transport::Error> for AdtError {
+    fn from(err: tonic::transport::Error) -> Self {
+        AdtError::Server(err.to_string())
+    }
+}
+
+impl From<arrow_flight::error::FlightError> for AdtError {
+    fn from(err: arrow_flight::error::FlightError) -> Self {
+        AdtError::Server(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AdtError {
+    fn from(err: serde_json::Error) -> Self {
+        AdtError::Table(err.to_string())
+    }
+}
+
+/// object_store reports rejected credentials as generic errors carrying the
+/// http status of the response
+fn is_auth_failure(err: &object_store::Error) -> bool {
+    let mut source: Option<&dyn std::error::Error> = Some(err);
+    while let Some(err) = source {
+        let message = err.to_string();
+        if [
+            "401 Unauthorized",
+            "403 Forbidden",
+            "InvalidAccessKeyId",
+            "ExpiredToken",
+        ]
+        .iter()
+        .any(|status| message.contains(status))
+        {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// Print a failure with its hint on stderr and return its exit code
+pub fn report(err: &AdtError) -> i32 {
+    eprintln!("Error: {}", err);
+    if let Some(hint) = err.hint() {
+        eprintln!("Hint: {}", hint);
+    }
+    err.exit_code()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn datafusion_errors_by_stage() {
+        let planning = AdtError::from(DataFusionError::Plan("no table".to_string()));
+        assert!(matches!(planning, AdtError::Planning(_)));
+        // the root cause decides, whatever its context
+        let nested = DataFusionError::Context(
+            "query".to_string(),
+            Box::new(DataFusionError::SchemaError(
+                datafusion::common::SchemaError::FieldNotFound {
+                    field: Box::new(datafusion::common::Column::from_name("x")),
+                    valid_fields: vec![],
+                },
+                Box::new(None),
+            )),
+        );
+        assert!(matches!(AdtError::from(nested), AdtError::Planning(_)));
+        let execution = AdtError::from(DataFusionError::Execution("overflow".to_string()));
+        assert!(matches!(execution, AdtError::Execution(_)));
+        let arrow = AdtError::from(ArrowError::DivideByZero);
+        assert!(matches!(arrow, AdtError::Execution(_)));
+    }
+
+    #[test]
+    fn object_store_errors_by_cause() {
+        let denied = object_store::Error::Generic {
+            store: "S3",
+            source: "Client error with status 403 Forbidden".into(),
+        };
+        assert!(matches!(
+            AdtError::from(denied),
+            AdtError::ObjectStoreAuth(_)
+        ));
+        let missing = object_store::Error::NotFound {
+            path: "bucket/key".to_string(),
+            source: "no such key".into(),
+        };
+        let err = AdtError::from(missing);
+        assert!(matches!(err, AdtError::ObjectStore(_)));
+        let wrapped = DataFusionError::ObjectStore(object_store::Error::Generic {
+            store: "S3",
+            source: "InvalidAccessKeyId".into(),
+        });
+        assert!(matches!(
+            AdtError::from(wrapped),
+            AdtError::ObjectStoreAuth(_)
+        ));
+    }
+
+    #[test]
+    fn delta_errors_keep_store_failures() {
+        let store = DeltaTableError::ObjectStore {
+            source: object_store::Error::NotFound {
+                path: "t/_delta_log".to_string(),
+                source: "missing".into(),
+            },
+        };
+        assert!(matches!(AdtError::from(store), AdtError::ObjectStore(_)));
+        let log = DeltaTableError::InvalidVersion(3);
+        assert!(matches!(AdtError::from(log), AdtError::DeltaLog(_)));
+    }
+
+    #[test]
+    fn other_errors() {
+        let io = std::io::Error::new(std::io::ErrorKind::BrokenPipe, "closed");
+        assert!(matches!(AdtError::from(io), AdtError::Io(_)));
+        let eof = rustyline::error::ReadlineError::Eof;
+        assert!(matches!(AdtError::from(eof), AdtError::Terminal(_)));
+        let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert!(matches!(AdtError::from(json), AdtError::Table(_)));
+    }
+
+    #[test]
+    fn report_exit_codes() {
+        let cases = [
+            (
+                AdtError::InvalidPath {
+                    path: "x".to_string(),
+                    reason: "bad".to_string(),
+                },
+                3,
+            ),
+            (AdtError::UnsupportedType("uuid".to_string()), 4),
+            (
+                AdtError::MalformedSpec {
+                    spec: "a".to_string(),
+                    reason: "no type".to_string(),
+                },
+                5,
+            ),
+            (AdtError::ObjectStoreAuth("denied".to_string()), 6),
+            (AdtError::ObjectStore("missing".to_string()), 7),
+            (AdtError::DeltaLog("no log".to_string()), 8),
+            (AdtError::Table("no table".to_string()), 9),
+            (
+                AdtError::Planning(DataFusionError::Plan("plan".to_string())),
+                10,
+            ),
+            (
+                AdtError::Execution(DataFusionError::Execution("run".to_string())),
+                11,
+            ),
+            (AdtError::Logger("set".to_string()), 1),
+            (AdtError::Export("exists".to_string()), 12),
+            (AdtError::Io(std::io::Error::other("io")), 13),
+            (AdtError::Server("bind".to_string()), 14),
+            (AdtError::Terminal("tty".to_string()), 15),
+        ];
+        for (err, code) in cases {
+            assert_eq!(report(&err), code, "{}", err);
+        }
+    }
+}
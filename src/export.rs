@@ -0,0 +1,556 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::common::config::{FormatOptions, TableParquetOptions};
+use datafusion::dataframe::DataFrameWriteOptions;
+use datafusion::datasource::listing::ListingTableUrl;
+use datafusion::datasource::streaming::StreamingTable;
+use datafusion::error::DataFusionError;
+use datafusion::execution::context::SessionState;
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::logical_expr::LogicalPlanBuilder;
+use datafusion::parquet::basic::Compression;
+use datafusion::parquet::file::properties::WriterProperties;
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::streaming::PartitionStream;
+use datafusion::prelude::{DataFrame, SessionContext};
+use deltalake::protocol::SaveMode;
+use deltalake::{DeltaOps, DeltaTableBuilder, DeltaTableError};
+use futures::stream;
+use log::{debug, info, warn};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use url::Url;
+
+use crate::error::{AdtError, Result};
+
+/// Format of the exported query results
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OutputFormat {
+    Csv,
+    /// newline delimited json
+    Json,
+    Parquet,
+    /// arrow ipc file (feather v2)
+    Arrow,
+    Delta,
+}
+
+impl OutputFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Arrow => "arrow",
+            OutputFormat::Delta => "delta",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            OutputFormat::Csv,
+            OutputFormat::Json,
+            OutputFormat::Parquet,
+            OutputFormat::Arrow,
+            OutputFormat::Delta,
+        ]
+        .into_iter()
+        .find(|fmt| fmt.name() == name)
+    }
+}
+
+/// Behaviour when the output delta table already exists
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WriteMode {
+    Append,
+    Overwrite,
+    #[default]
+    ErrorIfExists,
+}
+
+impl WriteMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            WriteMode::Append => "append",
+            WriteMode::Overwrite => "overwrite",
+            WriteMode::ErrorIfExists => "error-if-exists",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            WriteMode::Append,
+            WriteMode::Overwrite,
+            WriteMode::ErrorIfExists,
+        ]
+        .into_iter()
+        .find(|mode| mode.name() == name)
+    }
+}
+
+/// Options of the exports
+#[derive(Clone, Default)]
+pub struct ExportOptions {
+    /// format of the output, guessed from the output path extension when None
+    pub output_format: Option<OutputFormat>,
+    /// write mode of a delta output
+    pub mode: WriteMode,
+    /// columns to partition the output by
+    pub partition_by: Vec<String>,
+    /// parquet and delta compression codec: uncompressed, snappy, lz4,
+    /// gzip(level), zstd(level) or brotli(level)
+    pub compression: Option<String>,
+    /// maximum number of rows of parquet and delta row groups
+    pub row_group_size: Option<usize>,
+    /// disable parquet and delta dictionary encoding
+    pub no_dictionary: bool,
+}
+
+/// Output format given by --output-format or guessed from the path extension
+fn output_format(output_path: &str, options: &ExportOptions) -> Result<OutputFormat> {
+    if let Some(fmt) = options.output_format {
+        return Ok(fmt);
+    }
+    let ext = std::path::Path::new(output_path)
+        .extension()
+        .and_then(|ext| ext.to_str());
+    match ext {
+        Some("csv") => Ok(OutputFormat::Csv),
+        Some("json" | "ndjson" | "jsonl") => Ok(OutputFormat::Json),
+        Some("parquet") => Ok(OutputFormat::Parquet),
+        Some("arrow" | "feather" | "ipc") => Ok(OutputFormat::Arrow),
+        _ => Err(AdtError::Export(format!(
+            "Unsupported output format for {}, use --output-format",
+            output_path
+        ))),
+    }
+}
+
+/// Write the query results to `output_path`
+pub async fn export(df: DataFrame, output_path: &str, options: &ExportOptions) -> Result<()> {
+    let write_options = DataFrameWriteOptions::default()
+        .with_single_file_output(options.partition_by.is_empty())
+        .with_partition_by(options.partition_by.clone());
+    match output_format(output_path, options)? {
+        OutputFormat::Csv => {
+            info!("export to csv");
+            df.write_csv(output_path, write_options, None).await?;
+        }
+        OutputFormat::Json => {
+            info!("export to newline delimited json");
+            df.write_json(output_path, write_options, None).await?;
+        }
+        OutputFormat::Parquet => {
+            info!("export to parquet");
+            df.write_parquet(output_path, write_options, Some(parquet_options(options)))
+                .await?;
+        }
+        OutputFormat::Arrow => {
+            // DataFrame has no arrow writer, the COPY plan has one
+            info!("export to arrow ipc");
+            let (state, plan) = df.into_parts();
+            let copy = LogicalPlanBuilder::copy_to(
+                plan,
+                output_path.to_string(),
+                FormatOptions::ARROW,
+                HashMap::new(),
+                options.partition_by.clone(),
+            )?
+            .build()?;
+            DataFrame::new(state, copy).collect().await?;
+        }
+        OutputFormat::Delta => export_delta(df, output_path, options).await?,
+    }
+    Ok(())
+}
+
+fn parquet_options(options: &ExportOptions) -> TableParquetOptions {
+    let mut parquet_options = TableParquetOptions::default();
+    if let Some(compression) = &options.compression {
+        parquet_options.global.compression = Some(compression.clone());
+    }
+    if let Some(row_group_size) = options.row_group_size {
+        parquet_options.global.max_row_group_size = row_group_size;
+    }
+    if options.no_dictionary {
+        parquet_options.global.dictionary_enabled = Some(false);
+    }
+    parquet_options
+}
+
+/// Delta table at `output_path`, not created yet when its log is missing.
+///
+/// Remote tables are written through the object store registered in the
+/// session. S3 has no atomic rename to commit with: concurrent writers need the
+/// dynamodb locking provider (`AWS_S3_LOCKING_PROVIDER=dynamodb`), otherwise
+/// writes are refused unless `AWS_S3_ALLOW_UNSAFE_RENAME=true` states there is
+/// a single writer.
+async fn delta_ops(state: &SessionState, output_path: &str) -> Result<DeltaOps> {
+    let url = ListingTableUrl::parse(output_path)?;
+    if url.scheme() == "file" {
+        return Ok(DeltaOps::try_from_uri(output_path).await?);
+    }
+    if matches!(url.scheme(), "s3" | "s3a") {
+        let env_is = |key: &str, value: &str| {
+            std::env::var(key).is_ok_and(|v| v.eq_ignore_ascii_case(value))
+        };
+        if !env_is("AWS_S3_LOCKING_PROVIDER", "dynamodb")
+            && !env_is("AWS_S3_ALLOW_UNSAFE_RENAME", "true")
+        {
+            return Err(AdtError::Export(format!(
+                "Unable to write delta table {} safely: set AWS_S3_LOCKING_PROVIDER=dynamodb, \
+                or AWS_S3_ALLOW_UNSAFE_RENAME=true when there is a single writer",
+                output_path
+            )));
+        }
+    }
+    let store = state.runtime_env().object_store(url.object_store())?;
+    let location = Url::parse(output_path).map_err(|e| AdtError::InvalidPath {
+        path: output_path.to_string(),
+        reason: e.to_string(),
+    })?;
+    let mut table = DeltaTableBuilder::from_uri(output_path)
+        .with_storage_backend(store, location)
+        .build()?;
+    // the table is created by the first write
+    match table.load().await {
+        Ok(()) | Err(DeltaTableError::NotATable(_)) => Ok(DeltaOps::from(table)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+async fn export_delta(df: DataFrame, output_path: &str, options: &ExportOptions) -> Result<()> {
+    info!("export to delta table");
+    deltalake::aws::register_handlers(None);
+    let save_mode = match options.mode {
+        WriteMode::Append => SaveMode::Append,
+        WriteMode::Overwrite => SaveMode::Overwrite,
+        WriteMode::ErrorIfExists => SaveMode::ErrorIfExists,
+    };
+    let mut writer_properties = WriterProperties::builder();
+    if let Some(compression) = &options.compression {
+        let compression = Compression::from_str(compression).map_err(|err| {
+            AdtError::Export(format!(
+                "Invalid compression codec {}: {}",
+                compression, err
+            ))
+        })?;
+        writer_properties = writer_properties.set_compression(compression);
+    }
+    if let Some(row_group_size) = options.row_group_size {
+        writer_properties = writer_properties.set_max_row_group_size(row_group_size);
+    }
+    if options.no_dictionary {
+        writer_properties = writer_properties.set_dictionary_enabled(false);
+    }
+    let (state, plan) = df.into_parts();
+    let ops = delta_ops(&state, output_path).await?;
+    let exists = ops.0.version() >= 0;
+    if exists && options.mode == WriteMode::ErrorIfExists {
+        return Err(AdtError::Export(format!(
+            "Delta table {} already exists, use --mode append or --mode overwrite",
+            output_path
+        )));
+    }
+    // writes to an existing table keep its partitioning unless told otherwise
+    let partition_by = match ops.0.metadata() {
+        Ok(metadata) if options.partition_by.is_empty() => metadata.partition_columns.clone(),
+        _ => options.partition_by.clone(),
+    };
+    let physical_plan = state.create_physical_plan(&plan).await?;
+    let table = ops
+        .write(vec![])
+        .with_input_execution_plan(physical_plan)
+        .with_input_session_state(state)
+        .with_save_mode(save_mode)
+        .with_partition_columns(partition_by)
+        .with_writer_properties(writer_properties.build())
+        .await
+        .map_err(|err| match AdtError::from(err) {
+            AdtError::DeltaLog(reason) => AdtError::Export(format!(
+                "Unable to write delta table {}: {}",
+                output_path, reason
+            )),
+            err => err,
+        })?;
+    info!("delta table {} version: {}", output_path, table.version());
+    Ok(())
+}
+
+/// Batches given one at a time to a single use partition, so that the
+/// DataFrame writers can consume a stream that is also displayed
+struct ChannelPartition {
+    schema: SchemaRef,
+    receiver: Mutex<Option<mpsc::Receiver<RecordBatch>>>,
+}
+
+impl PartitionStream for ChannelPartition {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+        let Some(receiver) = self.receiver.lock().unwrap().take() else {
+            let error = DataFusionError::Execution("The export stream is read once".to_string());
+            return Box::pin(RecordBatchStreamAdapter::new(
+                self.schema.clone(),
+                stream::once(async { Err(error) }),
+            ));
+        };
+        let batches = stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|batch| (Ok(batch), receiver))
+        });
+        Box::pin(RecordBatchStreamAdapter::new(self.schema.clone(), batches))
+    }
+}
+
+/// Export running in the background while the query results are sent to it
+pub struct StreamExport {
+    sender: mpsc::Sender<RecordBatch>,
+    handle: JoinHandle<Result<()>>,
+    /// local output created by the export, removed when it is aborted
+    created: Option<PathBuf>,
+}
+
+impl StreamExport {
+    pub fn start(
+        ctx: &SessionContext,
+        schema: SchemaRef,
+        output_path: &str,
+        options: &ExportOptions,
+    ) -> Result<Self> {
+        let (sender, receiver) = mpsc::channel(2);
+        let partition = ChannelPartition {
+            schema: schema.clone(),
+            receiver: Mutex::new(Some(receiver)),
+        };
+        let table = StreamingTable::try_new(schema, vec![Arc::new(partition)])?;
+        let df = ctx.read_table(Arc::new(table))?;
+        // outputs that already exist, e.g. delta tables appended to, are kept
+        let created = Url::parse(output_path)
+            .ok()
+            .filter(|url| url.scheme() == "file")
+            .and_then(|url| url.to_file_path().ok())
+            .filter(|path| !path.exists());
+        let output_path = output_path.to_string();
+        let options = options.clone();
+        let handle = tokio::spawn(async move { export(df, &output_path, &options).await });
+        Ok(Self {
+            sender,
+            handle,
+            created,
+        })
+    }
+
+    pub async fn send(&self, batch: RecordBatch) -> Result<()> {
+        if self.sender.send(batch).await.is_err() {
+            // the writer stopped early, its error is reported by finish
+            debug!("export stream closed");
+        }
+        Ok(())
+    }
+
+    /// Wait for the end of the export once every batch is sent
+    pub async fn finish(self) -> Result<()> {
+        drop(self.sender);
+        self.handle.await?
+    }
+
+    /// Stop the export, e.g. when the query fails, so that no partial output
+    /// is committed. The local files written so far are removed.
+    pub async fn abort(self) {
+        self.handle.abort();
+        let _ = self.handle.await;
+        let Some(path) = self.created else {
+            return;
+        };
+        let removed = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else if path.exists() {
+            std::fs::remove_file(&path)
+        } else {
+            Ok(())
+        };
+        if let Err(err) = removed {
+            warn!(
+                "unable to remove partial output {}: {}",
+                path.display(),
+                err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::{Int64Array, StringArray};
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::ObjectStore;
+
+    // stands in for an s3 bucket
+    fn bucket_context() -> (SessionContext, Arc<InMemory>) {
+        let ctx = SessionContext::new();
+        let store = Arc::new(InMemory::new());
+        ctx.register_object_store(&Url::parse("s3://bucket").unwrap(), store.clone());
+        (ctx, store)
+    }
+
+    fn batch() -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]);
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec![Some("a"), None])),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn stream_export_to_object_store() {
+        let (ctx, store) = bucket_context();
+        let batch = batch();
+        let export = StreamExport::start(
+            &ctx,
+            batch.schema(),
+            "s3://bucket/out/result.csv",
+            &ExportOptions::default(),
+        )
+        .unwrap();
+        export.send(batch).await.unwrap();
+        export.finish().await.unwrap();
+        let content = store
+            .get(&Path::from("out/result.csv"))
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        assert_eq!(content.as_ref(), b"1,a\n2,\n");
+    }
+
+    #[tokio::test]
+    async fn export_parquet_to_object_store() {
+        let (ctx, store) = bucket_context();
+        let df = ctx.read_batch(batch()).unwrap();
+        let options = ExportOptions {
+            compression: Some("zstd(3)".to_string()),
+            ..Default::default()
+        };
+        export(df, "s3://bucket/result.parquet", &options)
+            .await
+            .unwrap();
+        let read = ctx
+            .read_parquet("s3://bucket/result.parquet", Default::default())
+            .await
+            .unwrap();
+        assert_eq!(read.count().await.unwrap(), 2);
+        assert!(store.head(&Path::from("result.parquet")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn abort_removes_local_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table");
+        let url = Url::from_file_path(&path).unwrap();
+        let options = ExportOptions {
+            output_format: Some(OutputFormat::Delta),
+            ..Default::default()
+        };
+        let batch = batch();
+        let export = StreamExport::start(
+            &SessionContext::new(),
+            batch.schema(),
+            url.as_str(),
+            &options,
+        )
+        .unwrap();
+        export.send(batch).await.unwrap();
+        for _ in 0..100 {
+            if path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(path.exists());
+        export.abort().await;
+        assert!(!path.exists());
+    }
+
+    async fn delta_rows(ctx: &SessionContext, path: &str) -> (i64, usize) {
+        let table = delta_ops(&ctx.state(), path).await.unwrap().0;
+        let version = table.version();
+        let rows = ctx
+            .read_table(Arc::new(table))
+            .unwrap()
+            .count()
+            .await
+            .unwrap();
+        (version, rows)
+    }
+
+    #[tokio::test]
+    async fn delta_write_modes() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = Url::from_file_path(dir.path().join("table")).unwrap();
+        let ctx = SessionContext::new();
+        let write = |mode| {
+            let options = ExportOptions {
+                output_format: Some(OutputFormat::Delta),
+                mode,
+                ..Default::default()
+            };
+            let df = ctx.read_batch(batch()).unwrap();
+            let url = url.clone();
+            async move { export(df, url.as_str(), &options).await }
+        };
+        write(WriteMode::ErrorIfExists).await.unwrap();
+        assert_eq!(delta_rows(&ctx, url.as_str()).await, (0, 2));
+        assert!(matches!(
+            write(WriteMode::ErrorIfExists).await,
+            Err(AdtError::Export(_))
+        ));
+        write(WriteMode::Append).await.unwrap();
+        assert_eq!(delta_rows(&ctx, url.as_str()).await, (1, 4));
+        write(WriteMode::Overwrite).await.unwrap();
+        assert_eq!(delta_rows(&ctx, url.as_str()).await, (2, 2));
+    }
+
+    // the s3 log store loads its sdk config by blocking on another task
+    #[tokio::test(flavor = "multi_thread")]
+    async fn export_delta_to_object_store() {
+        let (ctx, store) = bucket_context();
+        let options = ExportOptions {
+            output_format: Some(OutputFormat::Delta),
+            ..Default::default()
+        };
+        let write = || {
+            export(
+                ctx.read_batch(batch()).unwrap(),
+                "s3://bucket/table",
+                &options,
+            )
+        };
+        // no other test writes to s3 nor reads these variables
+        std::env::remove_var("AWS_S3_LOCKING_PROVIDER");
+        std::env::remove_var("AWS_S3_ALLOW_UNSAFE_RENAME");
+        assert!(matches!(write().await, Err(AdtError::Export(_))));
+        std::env::set_var("AWS_S3_ALLOW_UNSAFE_RENAME", "true");
+        write().await.unwrap();
+        let commit = Path::from("table/_delta_log/00000000000000000000.json");
+        assert!(store.head(&commit).await.is_ok());
+        assert_eq!(delta_rows(&ctx, "s3://bucket/table").await, (0, 2));
+    }
+}
@@ -0,0 +1,497 @@
+// tonic::Status is the error type imposed by the flight sql service trait
+#![allow(clippy::result_large_err)]
+
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use arrow::array::{ArrayRef, RecordBatch, StringArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::IpcWriteOptions;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_server::FlightServiceServer;
+use arrow_flight::sql::metadata::{SqlInfoData, SqlInfoDataBuilder};
+use arrow_flight::sql::server::FlightSqlService;
+use arrow_flight::sql::{
+    ActionClosePreparedStatementRequest, ActionCreatePreparedStatementRequest,
+    ActionCreatePreparedStatementResult, Any, CommandGetCatalogs, CommandGetDbSchemas,
+    CommandGetSqlInfo, CommandGetTableTypes, CommandGetTables, CommandPreparedStatementQuery,
+    CommandStatementQuery, ProstMessageExt, SqlInfo, TicketStatementQuery,
+};
+use arrow_flight::{
+    Action, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo, HandshakeRequest,
+    HandshakeResponse, IpcMessage, SchemaAsIpc, Ticket,
+};
+use datafusion::prelude::DataFrame;
+use futures::{stream, Stream, TryStreamExt};
+use log::{debug, info};
+use prost::bytes::Bytes;
+use prost::Message;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::error::{AdtError, Result};
+use crate::table::TableContext;
+
+type DoGetStream = Pin<Box<dyn Stream<Item = Result<FlightData, Status>> + Send>>;
+
+const TABLE_TYPES: [&str; 2] = ["TABLE", "VIEW"];
+
+/// Major version of the arrow dependency declared in Cargo.toml, to bump
+/// along with it
+const ARROW_VERSION: &str = "52";
+
+/// Planned queries kept for the clients, beyond it the oldest ones are evicted
+const MAX_PLANS: usize = 256;
+
+/// Queries planned once and run later by handle: clients may never fetch a
+/// ticket nor close a prepared statement, so only the last `MAX_PLANS` are kept
+#[derive(Default)]
+struct Plans {
+    plans: BTreeMap<u64, DataFrame>,
+    next_handle: u64,
+}
+
+impl Plans {
+    fn insert(&mut self, df: DataFrame) -> Bytes {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.plans.insert(handle, df);
+        while self.plans.len() > MAX_PLANS {
+            if let Some((evicted, _)) = self.plans.pop_first() {
+                debug!("evict plan {}", evicted);
+            }
+        }
+        handle.to_string().into_bytes().into()
+    }
+
+    fn get(&self, handle: &[u8]) -> Option<DataFrame> {
+        self.plans.get(&parse_handle(handle)?).cloned()
+    }
+
+    fn remove(&mut self, handle: &[u8]) -> Option<DataFrame> {
+        self.plans.remove(&parse_handle(handle)?)
+    }
+}
+
+fn parse_handle(handle: &[u8]) -> Option<u64> {
+    std::str::from_utf8(handle).ok()?.parse().ok()
+}
+
+fn unknown_handle(kind: &str, handle: &[u8]) -> Status {
+    Status::not_found(format!(
+        "Unknown or expired {} {}",
+        kind,
+        String::from_utf8_lossy(handle)
+    ))
+}
+
+/// Flight SQL service answering queries on the tables of a `TableContext`
+pub struct FlightSqlServer {
+    tblctx: Arc<TableContext>,
+    sql_info: SqlInfoData,
+    // prepared statements, run each time they are fetched
+    statements: Mutex<Plans>,
+    // statement queries planned by get_flight_info, run once by do_get
+    tickets: Mutex<Plans>,
+}
+
+impl FlightSqlServer {
+    pub fn new(tblctx: Arc<TableContext>) -> Result<Self> {
+        let mut builder = SqlInfoDataBuilder::new();
+        builder.append(SqlInfo::FlightSqlServerName, "adt");
+        builder.append(SqlInfo::FlightSqlServerVersion, env!("CARGO_PKG_VERSION"));
+        builder.append(SqlInfo::FlightSqlServerArrowVersion, ARROW_VERSION);
+        builder.append(SqlInfo::FlightSqlServerReadOnly, true);
+        Ok(Self {
+            tblctx,
+            sql_info: builder.build()?,
+            statements: Mutex::new(Plans::default()),
+            tickets: Mutex::new(Plans::default()),
+        })
+    }
+
+    async fn plan(&self, sql: &str) -> Result<DataFrame, Status> {
+        info!("query: {}", sql);
+        self.tblctx.read_only_query(sql).await.map_err(to_status)
+    }
+
+    fn statement(&self, handle: &[u8]) -> Result<DataFrame, Status> {
+        self.statements
+            .lock()
+            .unwrap()
+            .get(handle)
+            .ok_or_else(|| unknown_handle("prepared statement", handle))
+    }
+
+    async fn execute(&self, df: DataFrame) -> Result<Response<DoGetStream>, Status> {
+        let schema: SchemaRef = df.schema().inner().clone();
+        let batches = df
+            .execute_stream()
+            .await
+            .map_err(to_status)?
+            .map_err(|e| FlightError::ExternalError(Box::new(e)));
+        let stream = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(batches)
+            .map_err(Status::from);
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+fn to_status(err: impl Into<AdtError>) -> Status {
+    match err.into() {
+        err @ AdtError::Planning(_) => Status::invalid_argument(err.to_string()),
+        err => Status::internal(err.to_string()),
+    }
+}
+
+fn arrow_status(err: ArrowError) -> Status {
+    Status::internal(err.to_string())
+}
+
+fn flight_info(
+    schema: &Schema,
+    ticket: Any,
+    descriptor: FlightDescriptor,
+) -> Result<Response<FlightInfo>, Status> {
+    let endpoint = FlightEndpoint::new().with_ticket(Ticket::new(ticket.encode_to_vec()));
+    let info = FlightInfo::new()
+        .try_with_schema(schema)
+        .map_err(arrow_status)?
+        .with_endpoint(endpoint)
+        .with_descriptor(descriptor);
+    Ok(Response::new(info))
+}
+
+fn batch_stream(
+    schema: SchemaRef,
+    batch: Result<RecordBatch, FlightError>,
+) -> Response<DoGetStream> {
+    let stream = FlightDataEncoderBuilder::new()
+        .with_schema(schema)
+        .build(stream::once(async { batch }))
+        .map_err(Status::from);
+    Response::new(Box::pin(stream))
+}
+
+fn table_types_batch() -> Result<RecordBatch, Status> {
+    let schema = Schema::new(vec![Field::new("table_type", DataType::Utf8, false)]);
+    let types: ArrayRef = Arc::new(StringArray::from(TABLE_TYPES.to_vec()));
+    RecordBatch::try_new(Arc::new(schema), vec![types]).map_err(arrow_status)
+}
+
+#[tonic::async_trait]
+impl FlightSqlService for FlightSqlServer {
+    type FlightService = FlightSqlServer;
+
+    // no authentication, any client is accepted
+    async fn do_handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<
+        Response<Pin<Box<dyn Stream<Item = Result<HandshakeResponse, Status>> + Send>>>,
+        Status,
+    > {
+        let response = HandshakeResponse {
+            protocol_version: 0,
+            payload: Default::default(),
+        };
+        Ok(Response::new(Box::pin(stream::iter(vec![Ok(response)]))))
+    }
+
+    async fn get_flight_info_statement(
+        &self,
+        query: CommandStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let df = self.plan(&query.query).await?;
+        let schema = df.schema().inner().clone();
+        let ticket = TicketStatementQuery {
+            statement_handle: self.tickets.lock().unwrap().insert(df),
+        };
+        flight_info(&schema, ticket.as_any(), request.into_inner())
+    }
+
+    async fn get_flight_info_prepared_statement(
+        &self,
+        cmd: CommandPreparedStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let df = self.statement(&cmd.prepared_statement_handle)?;
+        flight_info(df.schema().as_arrow(), cmd.as_any(), request.into_inner())
+    }
+
+    async fn get_flight_info_catalogs(
+        &self,
+        query: CommandGetCatalogs,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder().schema();
+        flight_info(&schema, query.as_any(), request.into_inner())
+    }
+
+    async fn get_flight_info_schemas(
+        &self,
+        query: CommandGetDbSchemas,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder().schema();
+        flight_info(&schema, query.as_any(), request.into_inner())
+    }
+
+    async fn get_flight_info_tables(
+        &self,
+        query: CommandGetTables,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder().schema();
+        flight_info(&schema, query.as_any(), request.into_inner())
+    }
+
+    async fn get_flight_info_table_types(
+        &self,
+        query: CommandGetTableTypes,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let batch = table_types_batch()?;
+        flight_info(&batch.schema(), query.as_any(), request.into_inner())
+    }
+
+    async fn get_flight_info_sql_info(
+        &self,
+        query: CommandGetSqlInfo,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let schema = query.clone().into_builder(&self.sql_info).schema();
+        flight_info(&schema, query.as_any(), request.into_inner())
+    }
+
+    async fn do_get_statement(
+        &self,
+        ticket: TicketStatementQuery,
+        _request: Request<Ticket>,
+    ) -> Result<Response<DoGetStream>, Status> {
+        let df = self
+            .tickets
+            .lock()
+            .unwrap()
+            .remove(&ticket.statement_handle)
+            .ok_or_else(|| unknown_handle("ticket", &ticket.statement_handle))?;
+        self.execute(df).await
+    }
+
+    async fn do_get_prepared_statement(
+        &self,
+        query: CommandPreparedStatementQuery,
+        _request: Request<Ticket>,
+    ) -> Result<Response<DoGetStream>, Status> {
+        let df = self.statement(&query.prepared_statement_handle)?;
+        self.execute(df).await
+    }
+
+    async fn do_get_catalogs(
+        &self,
+        query: CommandGetCatalogs,
+        _request: Request<Ticket>,
+    ) -> Result<Response<DoGetStream>, Status> {
+        let mut builder = query.into_builder();
+        for catalog in self.tblctx.context().catalog_names() {
+            builder.append(catalog);
+        }
+        Ok(batch_stream(builder.schema(), builder.build()))
+    }
+
+    async fn do_get_schemas(
+        &self,
+        query: CommandGetDbSchemas,
+        _request: Request<Ticket>,
+    ) -> Result<Response<DoGetStream>, Status> {
+        let ctx = self.tblctx.context();
+        let mut builder = query.into_builder();
+        for catalog_name in ctx.catalog_names() {
+            if let Some(catalog) = ctx.catalog(&catalog_name) {
+                for schema_name in catalog.schema_names() {
+                    builder.append(&catalog_name, schema_name);
+                }
+            }
+        }
+        Ok(batch_stream(builder.schema(), builder.build()))
+    }
+
+    async fn do_get_tables(
+        &self,
+        query: CommandGetTables,
+        _request: Request<Ticket>,
+    ) -> Result<Response<DoGetStream>, Status> {
+        let ctx = self.tblctx.context();
+        let mut builder = query.into_builder();
+        for catalog_name in ctx.catalog_names() {
+            let Some(catalog) = ctx.catalog(&catalog_name) else {
+                continue;
+            };
+            for schema_name in catalog.schema_names() {
+                let Some(schema) = catalog.schema(&schema_name) else {
+                    continue;
+                };
+                for table_name in schema.table_names() {
+                    let Some(table) = schema.table(&table_name).await.map_err(to_status)? else {
+                        continue;
+                    };
+                    let table_type = match table.table_type() {
+                        datafusion::datasource::TableType::View => "VIEW",
+                        _ => "TABLE",
+                    };
+                    builder
+                        .append(
+                            &catalog_name,
+                            &schema_name,
+                            &table_name,
+                            table_type,
+                            &table.schema(),
+                        )
+                        .map_err(Status::from)?;
+                }
+            }
+        }
+        Ok(batch_stream(builder.schema(), builder.build()))
+    }
+
+    async fn do_get_table_types(
+        &self,
+        _query: CommandGetTableTypes,
+        _request: Request<Ticket>,
+    ) -> Result<Response<DoGetStream>, Status> {
+        let batch = table_types_batch()?;
+        Ok(batch_stream(batch.schema(), Ok(batch)))
+    }
+
+    async fn do_get_sql_info(
+        &self,
+        query: CommandGetSqlInfo,
+        _request: Request<Ticket>,
+    ) -> Result<Response<DoGetStream>, Status> {
+        let builder = query.into_builder(&self.sql_info);
+        Ok(batch_stream(builder.schema(), builder.build()))
+    }
+
+    async fn do_action_create_prepared_statement(
+        &self,
+        query: ActionCreatePreparedStatementRequest,
+        _request: Request<Action>,
+    ) -> Result<ActionCreatePreparedStatementResult, Status> {
+        let df = self.plan(&query.query).await?;
+        let IpcMessage(schema_bytes) =
+            SchemaAsIpc::new(df.schema().as_arrow(), &IpcWriteOptions::default())
+                .try_into()
+                .map_err(arrow_status)?;
+        let handle = self.statements.lock().unwrap().insert(df);
+        debug!(
+            "prepared statement {}: {}",
+            String::from_utf8_lossy(&handle),
+            query.query
+        );
+        Ok(ActionCreatePreparedStatementResult {
+            prepared_statement_handle: handle,
+            dataset_schema: schema_bytes,
+            parameter_schema: Default::default(),
+        })
+    }
+
+    async fn do_action_close_prepared_statement(
+        &self,
+        query: ActionClosePreparedStatementRequest,
+        _request: Request<Action>,
+    ) -> Result<(), Status> {
+        self.statements
+            .lock()
+            .unwrap()
+            .remove(&query.prepared_statement_handle);
+        Ok(())
+    }
+
+    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+}
+
+pub async fn serve(tblctx: Arc<TableContext>, host: &str, port: u16) -> Result<()> {
+    let addr = tokio::net::lookup_host((host, port))
+        .await?
+        .next()
+        .ok_or_else(|| AdtError::Server(format!("Unable to resolve {}:{}", host, port)))?;
+    let service = FlightServiceServer::new(FlightSqlServer::new(tblctx)?);
+    info!("flight sql listening on {}", addr);
+    Server::builder()
+        .add_service(service)
+        .serve_with_shutdown(addr, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> FlightSqlServer {
+        FlightSqlServer::new(Arc::new(TableContext::new(vec![]))).unwrap()
+    }
+
+    fn statement(query: &str) -> CommandStatementQuery {
+        CommandStatementQuery {
+            query: query.to_string(),
+            transaction_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn statement_planned_once_per_ticket() {
+        let server = server();
+        let info = server
+            .get_flight_info_statement(statement("select 1 as a"), Request::new(Default::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        let any = Any::decode(info.endpoint[0].ticket.as_ref().unwrap().ticket.clone()).unwrap();
+        let ticket: TicketStatementQuery = any.unpack().unwrap().unwrap();
+        let flight_data: Vec<FlightData> = server
+            .do_get_statement(ticket.clone(), Request::new(Default::default()))
+            .await
+            .unwrap()
+            .into_inner()
+            .try_collect()
+            .await
+            .unwrap();
+        assert!(!flight_data.is_empty());
+        let refetch = server
+            .do_get_statement(ticket, Request::new(Default::default()))
+            .await;
+        assert_eq!(refetch.err().unwrap().code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn statement_rejects_ddl() {
+        let status = server()
+            .get_flight_info_statement(
+                statement("create table t as select 1"),
+                Request::new(Default::default()),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn plans_evict_the_oldest() {
+        let df = server().plan("select 1").await.unwrap();
+        let mut plans = Plans::default();
+        let first = plans.insert(df.clone());
+        for _ in 0..MAX_PLANS {
+            plans.insert(df.clone());
+        }
+        assert_eq!(plans.plans.len(), MAX_PLANS);
+        assert!(plans.get(&first).is_none());
+    }
+}
@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use datafusion::arrow::array::{ArrayRef, Int64Array, StringArray, TimestampMillisecondArray};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use datafusion::arrow::record_batch::RecordBatch;
+use deltalake::kernel::{Action, CommitInfo};
+use deltalake::logstore::get_actions;
+use deltalake::DeltaTableBuilder;
+use log::{debug, info, warn};
+use serde_json::Value;
+
+use crate::error::{AdtError, Result};
+use crate::utils::ensure_scheme;
+
+/// A delta log commit along with the version it produced
+pub struct CommitEntry {
+    pub version: i64,
+    pub info: CommitInfo,
+}
+
+impl CommitEntry {
+    pub fn operation_metrics(&self) -> Option<&Value> {
+        self.info.info.get("operationMetrics")
+    }
+
+    pub fn engine_info(&self) -> Option<String> {
+        self.info.engine_info.clone().or_else(|| {
+            self.info
+                .info
+                .get("clientVersion")
+                .and_then(|v| v.as_str())
+                .map(String::from)
+        })
+    }
+
+    pub fn to_json(&self) -> Result<Value> {
+        let mut value = serde_json::to_value(&self.info)?;
+        if let Value::Object(ref mut obj) = value {
+            obj.insert("version".to_string(), Value::from(self.version));
+        }
+        Ok(value)
+    }
+}
+
+/// Read commit infos of a delta table, newest first.
+///
+/// `from_version` and `to_version` bound the versions (inclusive), `limit` caps
+/// the number of returned commits.
+pub async fn commit_history(
+    table_path: &str,
+    from_version: Option<i64>,
+    to_version: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<CommitEntry>> {
+    deltalake::aws::register_handlers(None);
+    let path = ensure_scheme(table_path)?;
+    let table = DeltaTableBuilder::from_uri(path.as_str())
+        .without_files()
+        .load()
+        .await
+        .map_err(AdtError::from)?;
+    let latest = table.version();
+    let to_version = to_version.map_or(latest, |v| v.min(latest));
+    let from_version = from_version.unwrap_or(0).max(0);
+    if from_version > to_version {
+        return Err(AdtError::DeltaLog(format!(
+            "Empty version range {}..={} (latest version is {})",
+            from_version, to_version, latest
+        )));
+    }
+    info!(
+        "read delta history from version {} to {}",
+        from_version, to_version
+    );
+
+    let log_store = table.log_store();
+    let mut entries = Vec::new();
+    for version in (from_version..=to_version).rev() {
+        if limit.is_some_and(|l| entries.len() >= l) {
+            break;
+        }
+        let Some(bytes) = log_store
+            .read_commit_entry(version)
+            .await
+            .map_err(AdtError::from)?
+        else {
+            // older commits have been removed by log retention
+            warn!("commit {} is no longer available in the delta log", version);
+            break;
+        };
+        debug!("read commit {}", version);
+        let commit_info = get_actions(version, bytes)
+            .await
+            .map_err(AdtError::from)?
+            .into_iter()
+            .find_map(|action| match action {
+                Action::CommitInfo(info) => Some(info),
+                _ => None,
+            });
+        entries.push(CommitEntry {
+            version,
+            info: commit_info.unwrap_or_default(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Build a record batch from commit entries for tabular display
+pub fn history_to_batch(entries: &[CommitEntry]) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("version", DataType::Int64, false),
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+            true,
+        ),
+        Field::new("operation", DataType::Utf8, true),
+        Field::new("user", DataType::Utf8, true),
+        Field::new("operation_parameters", DataType::Utf8, true),
+        Field::new("operation_metrics", DataType::Utf8, true),
+        Field::new("engine_info", DataType::Utf8, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Int64Array::from_iter_values(
+            entries.iter().map(|e| e.version),
+        )),
+        Arc::new(
+            TimestampMillisecondArray::from_iter(entries.iter().map(|e| e.info.timestamp))
+                .with_timezone("UTC"),
+        ),
+        Arc::new(StringArray::from_iter(
+            entries.iter().map(|e| e.info.operation.clone()),
+        )),
+        Arc::new(StringArray::from_iter(entries.iter().map(|e| {
+            e.info.user_name.clone().or_else(|| e.info.user_id.clone())
+        }))),
+        Arc::new(StringArray::from_iter(entries.iter().map(|e| {
+            e.info
+                .operation_parameters
+                .as_ref()
+                .map(|p| serde_json::to_string(p).unwrap_or_default())
+        }))),
+        Arc::new(StringArray::from_iter(
+            entries
+                .iter()
+                .map(|e| e.operation_metrics().map(|m| m.to_string())),
+        )),
+        Arc::new(StringArray::from_iter(
+            entries.iter().map(|e| e.engine_info()),
+        )),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deltalake::DeltaOps;
+
+    /// Delta table of versions 0 to 3, one single row commit each
+    async fn delta_table() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        for id in 0..4 {
+            let batch =
+                RecordBatch::try_new(schema.clone(), vec![Arc::new(Int64Array::from(vec![id]))])
+                    .unwrap();
+            DeltaOps::try_from_uri(dir.path().to_str().unwrap())
+                .await
+                .unwrap()
+                .write(vec![batch])
+                .await
+                .unwrap();
+        }
+        dir
+    }
+
+    fn versions(entries: &[CommitEntry]) -> Vec<i64> {
+        entries.iter().map(|e| e.version).collect()
+    }
+
+    #[tokio::test]
+    async fn history_range_and_limit() {
+        let dir = delta_table().await;
+        let path = dir.path().to_str().unwrap();
+        let all = commit_history(path, None, None, None).await.unwrap();
+        assert_eq!(versions(&all), vec![3, 2, 1, 0]);
+        assert_eq!(all[0].info.operation.as_deref(), Some("WRITE"));
+
+        let range = commit_history(path, Some(1), Some(2), None).await.unwrap();
+        assert_eq!(versions(&range), vec![2, 1]);
+        let past_latest = commit_history(path, Some(2), Some(10), None).await.unwrap();
+        assert_eq!(versions(&past_latest), vec![3, 2]);
+        let limited = commit_history(path, Some(1), None, Some(2)).await.unwrap();
+        assert_eq!(versions(&limited), vec![3, 2]);
+
+        let batch = history_to_batch(&limited).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+    }
+
+    #[tokio::test]
+    async fn inverted_history_range() {
+        let dir = delta_table().await;
+        let path = dir.path().to_str().unwrap();
+        let err = commit_history(path, Some(2), Some(1), None).await;
+        assert!(matches!(err, Err(AdtError::DeltaLog(_))));
+        let err = commit_history(path, Some(5), None, None).await;
+        assert!(matches!(err, Err(AdtError::DeltaLog(_))));
+    }
+}
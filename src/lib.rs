@@ -0,0 +1,44 @@
+//! Query local and S3 parquet, delta, csv, json, arrow and avro tables with
+//! datafusion, the library behind the `adt` command.
+//!
+//! Tables are described by [`table::TableSpec`]s and registered in a
+//! [`table::TableContext`], which runs the queries:
+//!
+//! ```no_run
+//! use adt::table::{Format, TableContext, TableSpec};
+//!
+//! # async fn run() -> adt::error::Result<()> {
+//! let events = TableSpec::new("events", "s3://bucket/events")?
+//!     .with_format(Format::Delta);
+//! let users = TableSpec::new("users", "/data/users")?.with_partitions("country:string")?;
+//! let tblctx = TableContext::new(vec![events, users]);
+//! tblctx.register_tables().await?;
+//! let df = tblctx
+//!     .exec_query("select * from events join users using (user_id)".to_string(), Some(50))
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Results are written with [`export::export`] or streamed to an
+//! [`export::StreamExport`], and shown with the [`tui`] viewer. The `adt`
+//! commands parsed into a [`cli::Cli`] are run by [`run`].
+
+pub mod cli;
+mod commands;
+mod context;
+pub mod error;
+pub mod export;
+mod flight;
+pub mod history;
+pub mod parquet_meta;
+mod postgres;
+pub mod profile;
+mod server;
+mod shell;
+pub mod table;
+pub mod tui;
+mod utils;
+mod view;
+
+pub use commands::run;
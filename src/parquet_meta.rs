@@ -0,0 +1,614 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Arc;
+
+use datafusion::arrow::array::{ArrayRef, BooleanArray, Float64Array, Int64Array, StringArray};
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::datasource::file_format::parquet::fetch_parquet_metadata;
+use datafusion::datasource::listing::ListingTableUrl;
+use datafusion::parquet::basic::{Compression, ConvertedType};
+use datafusion::parquet::file::metadata::{ColumnChunkMetaData, ParquetMetaData};
+use datafusion::parquet::file::statistics::Statistics;
+use deltalake::DeltaTableBuilder;
+use futures::{StreamExt, TryStreamExt};
+use log::{debug, info};
+use object_store::{ObjectMeta, ObjectStore};
+
+use crate::error::{AdtError, Result};
+use crate::table::TableContext;
+use crate::utils::ensure_scheme;
+
+/// Footer of a parquet file
+pub struct FileMetadata {
+    pub path: String,
+    pub size: usize,
+    pub metadata: ParquetMetaData,
+}
+
+/// Whether a listed file is a parquet data file
+fn is_parquet(meta: &ObjectMeta) -> bool {
+    meta.location.filename().is_some_and(|name| {
+        !name.starts_with(['_', '.']) && (name.ends_with(".parquet") || name.ends_with(".parq"))
+    })
+}
+
+/// Read the footers of a parquet file, of the parquet files of a directory or
+/// of the active files of a delta table, in path order
+pub async fn read_metadata(path: &str) -> Result<Vec<FileMetadata>> {
+    let url = ensure_scheme(path)?;
+    let tblctx = TableContext::new(Vec::new());
+    tblctx.register_object_store(&url)?;
+    let listing = ListingTableUrl::parse(url.as_str())?;
+    let store = tblctx.context().runtime_env().object_store(&listing)?;
+    let delta_log = listing.prefix().child("_delta_log");
+    let is_delta = matches!(store.list(Some(&delta_log)).next().await, Some(Ok(_)));
+
+    let (store, mut files): (Arc<dyn ObjectStore>, Vec<ObjectMeta>) = if is_delta {
+        info!("read the active files of delta table {}", url);
+        deltalake::aws::register_handlers(None);
+        let table = DeltaTableBuilder::from_uri(url.as_str()).load().await?;
+        // paths of the delta files are relative to the table root
+        let store = table.object_store();
+        let mut files = Vec::new();
+        for path in table.get_files_iter()? {
+            files.push(store.head(&path).await?);
+        }
+        (store, files)
+    } else if listing.is_collection() {
+        let files = store
+            .list(Some(listing.prefix()))
+            .try_filter(|meta| futures::future::ready(is_parquet(meta)))
+            .try_collect()
+            .await?;
+        (store, files)
+    } else {
+        let file = store.head(listing.prefix()).await?;
+        (store, vec![file])
+    };
+    if files.is_empty() {
+        return Err(AdtError::Table(format!("No parquet file found in {}", url)));
+    }
+    files.sort_by(|a, b| a.location.cmp(&b.location));
+
+    let mut metadata = Vec::with_capacity(files.len());
+    for file in files {
+        debug!("read parquet footer of {}", file.location);
+        let footer = fetch_parquet_metadata(store.as_ref(), &file, None).await?;
+        metadata.push(FileMetadata {
+            path: file.location.to_string(),
+            size: file.size,
+            metadata: footer,
+        });
+    }
+    Ok(metadata)
+}
+
+/// Min or max of a column chunk, as its physical value
+#[derive(Clone, PartialEq, PartialOrd)]
+enum StatValue {
+    Boolean(bool),
+    Int(i64),
+    Float(f64),
+    Bytes(Vec<u8>),
+}
+
+impl fmt::Display for StatValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatValue::Boolean(value) => write!(f, "{}", value),
+            StatValue::Int(value) => write!(f, "{}", value),
+            StatValue::Float(value) => write!(f, "{}", value),
+            StatValue::Bytes(bytes) => match std::str::from_utf8(bytes) {
+                Ok(text) => write!(f, "{}", text),
+                Err(_) => {
+                    write!(f, "0x")?;
+                    bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+                }
+            },
+        }
+    }
+}
+
+/// Min and max of a column chunk, when its statistics have them
+fn min_max(statistics: &Statistics) -> Option<(StatValue, StatValue)> {
+    if !statistics.has_min_max_set() {
+        return None;
+    }
+    let bounds = match statistics {
+        Statistics::Boolean(s) => (StatValue::Boolean(*s.min()), StatValue::Boolean(*s.max())),
+        Statistics::Int32(s) => (
+            StatValue::Int(*s.min() as i64),
+            StatValue::Int(*s.max() as i64),
+        ),
+        Statistics::Int64(s) => (StatValue::Int(*s.min()), StatValue::Int(*s.max())),
+        // legacy timestamps, not comparable as they are stored
+        Statistics::Int96(_) => return None,
+        Statistics::Float(s) => (
+            StatValue::Float(*s.min() as f64),
+            StatValue::Float(*s.max() as f64),
+        ),
+        Statistics::Double(s) => (StatValue::Float(*s.min()), StatValue::Float(*s.max())),
+        Statistics::ByteArray(s) => (
+            StatValue::Bytes(s.min_bytes().to_vec()),
+            StatValue::Bytes(s.max_bytes().to_vec()),
+        ),
+        Statistics::FixedLenByteArray(s) => (
+            StatValue::Bytes(s.min_bytes().to_vec()),
+            StatValue::Bytes(s.max_bytes().to_vec()),
+        ),
+    };
+    Some(bounds)
+}
+
+/// Codec name as given to --compression, without its level
+fn codec_name(compression: Compression) -> &'static str {
+    match compression {
+        Compression::UNCOMPRESSED => "uncompressed",
+        Compression::SNAPPY => "snappy",
+        Compression::GZIP(_) => "gzip",
+        Compression::LZO => "lzo",
+        Compression::BROTLI(_) => "brotli",
+        Compression::LZ4 => "lz4",
+        Compression::ZSTD(_) => "zstd",
+        Compression::LZ4_RAW => "lz4_raw",
+    }
+}
+
+/// Physical type of a column, followed by its converted type when it has one
+fn column_type(column: &ColumnChunkMetaData) -> String {
+    let descr = column.column_descr();
+    match descr.converted_type() {
+        ConvertedType::NONE => descr.physical_type().to_string(),
+        converted => format!("{} {}", descr.physical_type(), converted),
+    }
+}
+
+fn encodings(column: &ColumnChunkMetaData) -> Vec<String> {
+    column.encodings().iter().map(|e| e.to_string()).collect()
+}
+
+/// Part of the `total` chunks with a feature, as `count/total`
+fn presence(count: usize, total: usize) -> String {
+    format!("{}/{}", count, total)
+}
+
+/// Build a record batch with one row per file, along with a total row when
+/// there are several files
+pub fn files_to_batch(files: &[FileMetadata]) -> Result<RecordBatch> {
+    struct Row {
+        path: String,
+        size: i64,
+        version: Option<i64>,
+        created_by: Option<String>,
+        rows: i64,
+        row_groups: i64,
+        compressed: i64,
+        uncompressed: i64,
+        page_index: String,
+        bloom_filters: String,
+        key_value: Option<String>,
+    }
+    let mut rows: Vec<Row> = Vec::with_capacity(files.len() + 1);
+    let (mut chunks, mut indexed, mut bloom) = (0, 0, 0);
+    for file in files {
+        let metadata = &file.metadata;
+        let file_metadata = metadata.file_metadata();
+        let columns = || metadata.row_groups().iter().flat_map(|rg| rg.columns());
+        let file_chunks = columns().count();
+        let file_indexed = columns()
+            .filter(|c| c.column_index_offset().is_some() && c.offset_index_offset().is_some())
+            .count();
+        let file_bloom = columns()
+            .filter(|c| c.bloom_filter_offset().is_some())
+            .count();
+        (chunks, indexed, bloom) = (
+            chunks + file_chunks,
+            indexed + file_indexed,
+            bloom + file_bloom,
+        );
+        let key_value = file_metadata.key_value_metadata().map(|key_value| {
+            let object: serde_json::Map<String, serde_json::Value> = key_value
+                .iter()
+                .map(|kv| (kv.key.clone(), serde_json::Value::from(kv.value.clone())))
+                .collect();
+            serde_json::Value::Object(object).to_string()
+        });
+        rows.push(Row {
+            path: file.path.clone(),
+            size: file.size as i64,
+            version: Some(file_metadata.version() as i64),
+            created_by: file_metadata.created_by().map(String::from),
+            rows: file_metadata.num_rows(),
+            row_groups: metadata.num_row_groups() as i64,
+            compressed: metadata
+                .row_groups()
+                .iter()
+                .map(|rg| rg.compressed_size())
+                .sum(),
+            uncompressed: metadata
+                .row_groups()
+                .iter()
+                .map(|rg| rg.total_byte_size())
+                .sum(),
+            page_index: presence(file_indexed, file_chunks),
+            bloom_filters: presence(file_bloom, file_chunks),
+            key_value,
+        });
+    }
+    if rows.len() > 1 {
+        let writers: BTreeSet<&str> = rows
+            .iter()
+            .filter_map(|r| r.created_by.as_deref())
+            .collect();
+        let total = Row {
+            path: format!("total of {} files", rows.len()),
+            size: rows.iter().map(|r| r.size).sum(),
+            version: None,
+            created_by: Some(writers.into_iter().collect::<Vec<_>>().join(", ")),
+            rows: rows.iter().map(|r| r.rows).sum(),
+            row_groups: rows.iter().map(|r| r.row_groups).sum(),
+            compressed: rows.iter().map(|r| r.compressed).sum(),
+            uncompressed: rows.iter().map(|r| r.uncompressed).sum(),
+            page_index: presence(indexed, chunks),
+            bloom_filters: presence(bloom, chunks),
+            key_value: None,
+        };
+        rows.push(total);
+    }
+
+    let schema = Schema::new(vec![
+        Field::new("path", DataType::Utf8, false),
+        Field::new("size", DataType::Int64, false),
+        Field::new("version", DataType::Int64, true),
+        Field::new("created_by", DataType::Utf8, true),
+        Field::new("rows", DataType::Int64, false),
+        Field::new("row_groups", DataType::Int64, false),
+        Field::new("compressed", DataType::Int64, false),
+        Field::new("uncompressed", DataType::Int64, false),
+        Field::new("page_index", DataType::Utf8, false),
+        Field::new("bloom_filters", DataType::Utf8, false),
+        Field::new("key_value", DataType::Utf8, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.path))),
+        Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.size))),
+        Arc::new(Int64Array::from_iter(rows.iter().map(|r| r.version))),
+        Arc::new(StringArray::from_iter(
+            rows.iter().map(|r| r.created_by.clone()),
+        )),
+        Arc::new(Int64Array::from_iter_values(rows.iter().map(|r| r.rows))),
+        Arc::new(Int64Array::from_iter_values(
+            rows.iter().map(|r| r.row_groups),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            rows.iter().map(|r| r.compressed),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            rows.iter().map(|r| r.uncompressed),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| &r.page_index),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| &r.bloom_filters),
+        )),
+        Arc::new(StringArray::from_iter(
+            rows.iter().map(|r| r.key_value.clone()),
+        )),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Build a record batch with one row per column chunk of each row group
+pub fn row_groups_to_batch(files: &[FileMetadata]) -> Result<RecordBatch> {
+    let chunks: Vec<(&str, usize, i64, &ColumnChunkMetaData)> = files
+        .iter()
+        .flat_map(|file| {
+            let row_groups = file.metadata.row_groups().iter().enumerate();
+            row_groups.flat_map(move |(index, rg)| {
+                rg.columns()
+                    .iter()
+                    .map(move |column| (file.path.as_str(), index, rg.num_rows(), column))
+            })
+        })
+        .collect();
+    let bounds: Vec<Option<(StatValue, StatValue)>> = chunks
+        .iter()
+        .map(|(_, _, _, c)| c.statistics().and_then(min_max))
+        .collect();
+
+    let schema = Schema::new(vec![
+        Field::new("path", DataType::Utf8, false),
+        Field::new("row_group", DataType::Int64, false),
+        Field::new("rows", DataType::Int64, false),
+        Field::new("column", DataType::Utf8, false),
+        Field::new("type", DataType::Utf8, false),
+        Field::new("codec", DataType::Utf8, false),
+        Field::new("encodings", DataType::Utf8, false),
+        Field::new("values", DataType::Int64, false),
+        Field::new("nulls", DataType::Int64, true),
+        Field::new("min", DataType::Utf8, true),
+        Field::new("max", DataType::Utf8, true),
+        Field::new("compressed", DataType::Int64, false),
+        Field::new("uncompressed", DataType::Int64, false),
+        Field::new("column_index", DataType::Boolean, false),
+        Field::new("offset_index", DataType::Boolean, false),
+        Field::new("bloom_filter", DataType::Boolean, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(chunks.iter().map(|c| c.0))),
+        Arc::new(Int64Array::from_iter_values(
+            chunks.iter().map(|c| c.1 as i64),
+        )),
+        Arc::new(Int64Array::from_iter_values(chunks.iter().map(|c| c.2))),
+        Arc::new(StringArray::from_iter_values(
+            chunks.iter().map(|c| c.3.column_path().string()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            chunks.iter().map(|c| column_type(c.3)),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            chunks.iter().map(|c| codec_name(c.3.compression())),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            chunks.iter().map(|c| encodings(c.3).join(", ")),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            chunks.iter().map(|c| c.3.num_values()),
+        )),
+        Arc::new(Int64Array::from_iter(
+            chunks
+                .iter()
+                .map(|c| c.3.statistics().map(|s| s.null_count() as i64)),
+        )),
+        Arc::new(StringArray::from_iter(
+            bounds
+                .iter()
+                .map(|b| b.as_ref().map(|(min, _)| min.to_string())),
+        )),
+        Arc::new(StringArray::from_iter(
+            bounds
+                .iter()
+                .map(|b| b.as_ref().map(|(_, max)| max.to_string())),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            chunks.iter().map(|c| c.3.compressed_size()),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            chunks.iter().map(|c| c.3.uncompressed_size()),
+        )),
+        Arc::new(BooleanArray::from_iter(
+            chunks
+                .iter()
+                .map(|c| Some(c.3.column_index_offset().is_some())),
+        )),
+        Arc::new(BooleanArray::from_iter(
+            chunks
+                .iter()
+                .map(|c| Some(c.3.offset_index_offset().is_some())),
+        )),
+        Arc::new(BooleanArray::from_iter(
+            chunks
+                .iter()
+                .map(|c| Some(c.3.bloom_filter_offset().is_some())),
+        )),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Column chunks of every row group and file, merged by column path
+struct ColumnSummary {
+    path: String,
+    column_type: String,
+    codecs: BTreeSet<&'static str>,
+    encodings: BTreeSet<String>,
+    chunks: usize,
+    values: i64,
+    /// unknown when a chunk has no statistics
+    nulls: Option<i64>,
+    min: Option<StatValue>,
+    max: Option<StatValue>,
+    /// whether a chunk has no min or max, so that they are unknown
+    unbounded: bool,
+    compressed: i64,
+    uncompressed: i64,
+    page_index: usize,
+    bloom_filters: usize,
+}
+
+impl ColumnSummary {
+    fn new(path: String, column_type: String) -> Self {
+        Self {
+            path,
+            column_type,
+            codecs: BTreeSet::new(),
+            encodings: BTreeSet::new(),
+            chunks: 0,
+            values: 0,
+            nulls: Some(0),
+            min: None,
+            max: None,
+            unbounded: false,
+            compressed: 0,
+            uncompressed: 0,
+            page_index: 0,
+            bloom_filters: 0,
+        }
+    }
+
+    fn add(&mut self, column: &ColumnChunkMetaData) {
+        self.codecs.insert(codec_name(column.compression()));
+        self.encodings.extend(encodings(column));
+        self.chunks += 1;
+        self.values += column.num_values();
+        let statistics = column.statistics();
+        self.nulls = self
+            .nulls
+            .zip(statistics)
+            .map(|(nulls, s)| nulls + s.null_count() as i64);
+        match statistics.and_then(min_max) {
+            Some((min, max)) => {
+                let lower = |current: &StatValue| min.partial_cmp(current) == Some(Ordering::Less);
+                let higher =
+                    |current: &StatValue| max.partial_cmp(current) == Some(Ordering::Greater);
+                if self.min.as_ref().is_none_or(lower) {
+                    self.min = Some(min.clone());
+                }
+                if self.max.as_ref().is_none_or(higher) {
+                    self.max = Some(max.clone());
+                }
+            }
+            None => self.unbounded = true,
+        }
+        self.compressed += column.compressed_size();
+        self.uncompressed += column.uncompressed_size();
+        if column.column_index_offset().is_some() && column.offset_index_offset().is_some() {
+            self.page_index += 1;
+        }
+        if column.bloom_filter_offset().is_some() {
+            self.bloom_filters += 1;
+        }
+    }
+}
+
+/// Build a record batch with one row per column, its chunks being merged
+/// over the row groups of every file
+pub fn columns_to_batch(files: &[FileMetadata]) -> Result<RecordBatch> {
+    let mut summaries: Vec<ColumnSummary> = Vec::new();
+    for file in files {
+        for rg in file.metadata.row_groups() {
+            for column in rg.columns() {
+                let path = column.column_path().string();
+                let position = match summaries.iter().position(|s| s.path == path) {
+                    Some(position) => position,
+                    None => {
+                        summaries.push(ColumnSummary::new(path, column_type(column)));
+                        summaries.len() - 1
+                    }
+                };
+                summaries[position].add(column);
+            }
+        }
+    }
+    let bound = |s: &ColumnSummary, value: &Option<StatValue>| {
+        value
+            .as_ref()
+            .filter(|_| !s.unbounded)
+            .map(|value| value.to_string())
+    };
+
+    let schema = Schema::new(vec![
+        Field::new("column", DataType::Utf8, false),
+        Field::new("type", DataType::Utf8, false),
+        Field::new("codecs", DataType::Utf8, false),
+        Field::new("encodings", DataType::Utf8, false),
+        Field::new("chunks", DataType::Int64, false),
+        Field::new("values", DataType::Int64, false),
+        Field::new("nulls", DataType::Int64, true),
+        Field::new("min", DataType::Utf8, true),
+        Field::new("max", DataType::Utf8, true),
+        Field::new("compressed", DataType::Int64, false),
+        Field::new("uncompressed", DataType::Int64, false),
+        Field::new("ratio", DataType::Float64, true),
+        Field::new("page_index", DataType::Utf8, false),
+        Field::new("bloom_filters", DataType::Utf8, false),
+    ]);
+    let s = &summaries;
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(s.iter().map(|s| &s.path))),
+        Arc::new(StringArray::from_iter_values(
+            s.iter().map(|s| &s.column_type),
+        )),
+        Arc::new(StringArray::from_iter_values(s.iter().map(|s| {
+            s.codecs.iter().copied().collect::<Vec<_>>().join(", ")
+        }))),
+        Arc::new(StringArray::from_iter_values(s.iter().map(|s| {
+            s.encodings.iter().cloned().collect::<Vec<_>>().join(", ")
+        }))),
+        Arc::new(Int64Array::from_iter_values(
+            s.iter().map(|s| s.chunks as i64),
+        )),
+        Arc::new(Int64Array::from_iter_values(s.iter().map(|s| s.values))),
+        Arc::new(Int64Array::from_iter(s.iter().map(|s| s.nulls))),
+        Arc::new(StringArray::from_iter(s.iter().map(|s| bound(s, &s.min)))),
+        Arc::new(StringArray::from_iter(s.iter().map(|s| bound(s, &s.max)))),
+        Arc::new(Int64Array::from_iter_values(s.iter().map(|s| s.compressed))),
+        Arc::new(Int64Array::from_iter_values(
+            s.iter().map(|s| s.uncompressed),
+        )),
+        Arc::new(Float64Array::from_iter(s.iter().map(|s| {
+            (s.compressed > 0).then(|| s.uncompressed as f64 / s.compressed as f64)
+        }))),
+        Arc::new(StringArray::from_iter_values(
+            s.iter().map(|s| presence(s.page_index, s.chunks)),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            s.iter().map(|s| presence(s.bloom_filters, s.chunks)),
+        )),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::Int32Array;
+    use datafusion::parquet::arrow::ArrowWriter;
+    use datafusion::parquet::file::properties::WriterProperties;
+
+    fn value(batch: &RecordBatch, col: &str, row: usize) -> Option<String> {
+        let column = batch.column_by_name(col).unwrap();
+        (!column.is_null(row))
+            .then(|| datafusion::arrow::util::display::array_value_to_string(column, row).unwrap())
+    }
+
+    #[tokio::test]
+    async fn columns_of_row_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.parquet");
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![3, 1, 4, 2, 5])),
+                Arc::new(StringArray::from(vec![
+                    Some("c"),
+                    None,
+                    Some("a"),
+                    None,
+                    Some("b"),
+                ])),
+            ],
+        )
+        .unwrap();
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(2)
+            .build();
+        let mut writer =
+            ArrowWriter::try_new(std::fs::File::create(&path).unwrap(), schema, Some(props))
+                .unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let files = read_metadata(path.to_str().unwrap()).await.unwrap();
+        assert_eq!(files.len(), 1);
+        let columns = columns_to_batch(&files).unwrap();
+        assert_eq!(columns.num_rows(), 2);
+        assert_eq!(value(&columns, "column", 0).as_deref(), Some("id"));
+        assert_eq!(value(&columns, "column", 1).as_deref(), Some("name"));
+        // one chunk per row group of 2 rows, merged
+        assert_eq!(value(&columns, "chunks", 0).as_deref(), Some("3"));
+        assert_eq!(value(&columns, "values", 0).as_deref(), Some("5"));
+        assert_eq!(value(&columns, "nulls", 0).as_deref(), Some("0"));
+        assert_eq!(value(&columns, "nulls", 1).as_deref(), Some("2"));
+        assert_eq!(value(&columns, "min", 0).as_deref(), Some("1"));
+        assert_eq!(value(&columns, "max", 0).as_deref(), Some("5"));
+        assert_eq!(value(&columns, "min", 1).as_deref(), Some("a"));
+        assert_eq!(value(&columns, "max", 1).as_deref(), Some("c"));
+    }
+}
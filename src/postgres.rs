@@ -0,0 +1,1037 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use arrow::array::{Array, ArrayRef, AsArray};
+use arrow::compute::cast;
+use arrow::datatypes::{
+    DataType, Date32Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type,
+    Time64MicrosecondType, TimeUnit, TimestampMicrosecondType,
+};
+use arrow::record_batch::RecordBatch;
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use async_trait::async_trait;
+use bytes::{BufMut, BytesMut};
+use chrono::{NaiveDate, NaiveDateTime};
+use datafusion::common::ScalarValue;
+use datafusion::error::DataFusionError;
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::logical_expr::LogicalPlan;
+use datafusion::prelude::DataFrame;
+use datafusion::sql::parser::DFParser;
+use datafusion::sql::sqlparser::ast::{
+    BinaryOperator, Expr, SelectItem, SetExpr, Statement, TableFactor, Value,
+};
+use datafusion::sql::sqlparser::dialect::PostgreSqlDialect;
+use datafusion::sql::sqlparser::parser::Parser;
+use futures::stream::BoxStream;
+use futures::{stream, Sink, SinkExt, Stream, StreamExt};
+use log::{debug, info, warn};
+use pgwire::api::auth::{
+    finish_authentication, save_startup_parameters_to_metadata, DefaultServerParameterProvider,
+    StartupHandler,
+};
+use pgwire::api::portal::{Format, Portal};
+use pgwire::api::query::{send_execution_response, ExtendedQueryHandler, SimpleQueryHandler};
+use pgwire::api::results::{
+    DataRowEncoder, DescribePortalResponse, DescribeStatementResponse, FieldFormat, FieldInfo,
+    QueryResponse, Response, Tag,
+};
+use pgwire::api::stmt::{NoopQueryParser, StoredStatement};
+use pgwire::api::store::PortalStore;
+use pgwire::api::{ClientInfo, ClientPortalStore, Type, DEFAULT_NAME};
+use pgwire::error::{ErrorInfo, PgWireError, PgWireResult};
+use pgwire::messages::data::DataRow;
+use pgwire::messages::extendedquery::{Execute, PortalSuspended};
+use pgwire::messages::response::EmptyQueryResponse;
+use pgwire::messages::{PgWireBackendMessage, PgWireFrontendMessage};
+use pgwire::tokio::process_socket;
+use pgwire::types::ToSqlText;
+use postgres_types::{accepts, to_sql_checked, IsNull, ToSql};
+
+use crate::error::Result;
+use crate::table::{read_only_options, TableContext};
+
+/// Version reported to clients, psql picks its catalog queries from it
+const SERVER_VERSION: &str = "15.0";
+
+/// Oid of the first registered table, the ones below are reserved by postgres
+const FIRST_OID: u32 = 16384;
+const EMPTY_QUERY: &str = "SELECT NULL AS none WHERE false";
+
+/// Statements acknowledged without doing anything: adt sessions are read only
+/// and have no transactions nor postgres settings
+const IGNORED_STATEMENTS: [&str; 6] = ["SET", "BEGIN", "START", "COMMIT", "ROLLBACK", "DISCARD"];
+
+/// Rows left to fetch from a portal suspended by an `Execute` limited in rows
+struct Cursor {
+    // the bound portal, a new bind of the same name starts a new query
+    portal: Arc<Portal<String>>,
+    rows: BoxStream<'static, PgWireResult<DataRow>>,
+}
+
+/// Postgres wire protocol handler running queries on a `TableContext`
+pub struct PgServer {
+    tblctx: Arc<TableContext>,
+    query_parser: Arc<NoopQueryParser>,
+    // suspended portals by client and portal name
+    cursors: Mutex<HashMap<(SocketAddr, String), Cursor>>,
+}
+
+impl PgServer {
+    pub fn new(tblctx: Arc<TableContext>) -> Self {
+        Self {
+            tblctx,
+            query_parser: Arc::new(NoopQueryParser::new()),
+            cursors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Plan a query, the session being shared by every client anything else
+    /// is rejected
+    async fn plan(&self, sql: &str) -> PgWireResult<LogicalPlan> {
+        debug!("plan query: {}", sql);
+        let plan = self
+            .tblctx
+            .context()
+            .state()
+            .create_logical_plan(sql)
+            .await
+            .map_err(user_error)?;
+        read_only_options().verify_plan(&plan).map_err(user_error)?;
+        Ok(plan)
+    }
+
+    async fn execute(
+        &self,
+        sql: &str,
+        params: Vec<ScalarValue>,
+        format: &Format,
+    ) -> PgWireResult<Response<'static>> {
+        info!("query: {}", sql);
+        if let Some(tag) = ignored_statement(sql) {
+            return Ok(Response::Execution(Tag::new(&tag)));
+        }
+        let plan = self.plan(sql).await?;
+        let mut df: DataFrame = self
+            .tblctx
+            .context()
+            .execute_logical_plan(plan)
+            .await
+            .map_err(user_error)?;
+        if !params.is_empty() {
+            df = df.with_param_values(params).map_err(user_error)?;
+        }
+        let fields = Arc::new(field_infos(df.schema().as_arrow(), format)?);
+        let batches = df.execute_stream().await.map_err(user_error)?;
+        Ok(Response::Query(QueryResponse::new(
+            fields.clone(),
+            encode_stream(batches, fields).boxed(),
+        )))
+    }
+
+    /// Run the statement bound to a portal, rewritten when it reads the catalog
+    async fn execute_portal(&self, portal: &Portal<String>) -> PgWireResult<Response<'static>> {
+        let sql = self
+            .catalog_query(&portal.statement.statement)
+            .unwrap_or_else(|| portal.statement.statement.clone());
+        let params = self.parameters(portal).await?;
+        self.execute(&sql, params, &portal.result_column_format)
+            .await
+    }
+
+    /// Forget the suspended portals of a closed connection
+    fn close_cursors(&self, addr: SocketAddr) {
+        self.cursors
+            .lock()
+            .unwrap()
+            .retain(|(client, _), _| *client != addr);
+    }
+
+    /// Decode bound parameters to the types expected by the plan
+    async fn parameters(&self, portal: &Portal<String>) -> PgWireResult<Vec<ScalarValue>> {
+        if portal.parameter_len() == 0 {
+            return Ok(vec![]);
+        }
+        let types = self.parameter_types(&portal.statement).await?;
+        let mut values = Vec::with_capacity(portal.parameter_len());
+        for (idx, (pg_type, data_type)) in types.iter().enumerate() {
+            let value = if portal.parameter_format.format_for(idx) == FieldFormat::Text {
+                let text = portal.parameters[idx]
+                    .as_ref()
+                    .map(|b| String::from_utf8_lossy(b).to_string());
+                ScalarValue::Utf8(text)
+            } else {
+                binary_parameter(portal, idx, pg_type)?
+            };
+            values.push(value.cast_to(data_type).map_err(user_error)?);
+        }
+        Ok(values)
+    }
+
+    /// Parameter types given by the client, or inferred from the plan
+    async fn parameter_types(
+        &self,
+        stmt: &StoredStatement<String>,
+    ) -> PgWireResult<Vec<(Type, DataType)>> {
+        let plan = self.plan(&stmt.statement).await?;
+        let mut inferred: Vec<(String, Option<DataType>)> = plan
+            .get_parameter_types()
+            .map_err(user_error)?
+            .into_iter()
+            .collect();
+        // placeholders are named $1, $2...
+        inferred.sort_by_key(|(name, _)| name[1..].parse::<usize>().unwrap_or(usize::MAX));
+        Ok(inferred
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (_, data_type))| {
+                let data_type = data_type.unwrap_or(DataType::Utf8);
+                let pg_type = stmt
+                    .parameter_types
+                    .get(idx)
+                    .filter(|t| **t != Type::UNKNOWN)
+                    .cloned()
+                    .unwrap_or_else(|| pg_type(&data_type));
+                (pg_type, data_type)
+            })
+            .collect())
+    }
+
+    /// Registered tables sorted by name along with the oid exposed to clients
+    fn table_oids(&self) -> Vec<(u32, String)> {
+        let mut names: Vec<String> = self
+            .tblctx
+            .tables()
+            .iter()
+            .map(|t| t.registered_name())
+            .collect();
+        names.sort();
+        names
+            .into_iter()
+            .enumerate()
+            .map(|(idx, name)| (FIRST_OID + idx as u32, name))
+            .collect()
+    }
+
+    fn table_of_oid(&self, oid: Option<&str>) -> Option<String> {
+        let oid = oid?.parse::<u32>().ok()?;
+        self.table_oids()
+            .into_iter()
+            .find(|(table_oid, _)| *table_oid == oid)
+            .map(|(_, name)| name)
+    }
+
+    /// Rewrite the pg_catalog queries sent by psql meta commands (`\d`, `\dt`,
+    /// `\d table`) to information_schema ones, other catalog queries get an
+    /// empty result
+    fn catalog_query(&self, sql: &str) -> Option<String> {
+        if !sql.contains("pg_catalog.pg_") {
+            return None;
+        }
+        debug!("catalog query: {}", sql);
+        let Some(query) = CatalogQuery::parse(sql) else {
+            return Some(EMPTY_QUERY.to_string());
+        };
+        let has_column = |name: &str| query.columns.iter().any(|c| c == name);
+        let name_filter = query
+            .name_pattern
+            .as_deref()
+            .map(|pattern| format!("AND table_name ~ {}", sql_string(pattern)))
+            .unwrap_or_default();
+        let rewritten = match query.relation.as_str() {
+            "pg_namespace" if has_column("Name") => {
+                // list schemas
+                "SELECT schema_name AS \"Name\", 'adt' AS \"Owner\" FROM information_schema.schemata \
+                WHERE schema_name <> 'information_schema' ORDER BY 1"
+                    .to_string()
+            }
+            "pg_class" if has_column("Name") => {
+                // list relations, only tables and views exist
+                let has_kind =
+                    |kinds: &[&str]| query.kinds.iter().any(|k| kinds.contains(&k.as_str()));
+                let kind_filter = match (has_kind(&["r", "p"]), has_kind(&["v"])) {
+                    _ if query.kinds.is_empty() => "",
+                    (true, true) => "",
+                    (true, false) => "AND table_type = 'BASE TABLE'",
+                    (false, true) => "AND table_type = 'VIEW'",
+                    (false, false) => "AND false",
+                };
+                format!(
+                    "SELECT table_schema AS \"Schema\", table_name AS \"Name\", \
+                    CASE table_type WHEN 'VIEW' THEN 'view' ELSE 'table' END AS \"Type\", \
+                    'adt' AS \"Owner\" FROM information_schema.tables \
+                    WHERE table_schema <> 'information_schema' {} {} ORDER BY 1, 2",
+                    kind_filter, name_filter
+                )
+            }
+            "pg_class" if has_column("oid") && has_column("relname") => {
+                // table lookup by name, answered with the oids of registered tables
+                let oids = self
+                    .table_oids()
+                    .into_iter()
+                    .map(|(oid, name)| format!("({}, 'public', {})", oid, sql_string(&name)))
+                    .collect::<Vec<_>>();
+                if oids.is_empty() {
+                    EMPTY_QUERY.to_string()
+                } else {
+                    format!(
+                        "SELECT column1 AS oid, column2 AS nspname, column3 AS relname \
+                        FROM (VALUES {}) WHERE column3 ~ {} ORDER BY 2, 3",
+                        oids.join(", "),
+                        sql_string(query.name_pattern.as_deref().unwrap_or(".*"))
+                    )
+                }
+            }
+            "pg_class" if has_column("relchecks") => {
+                // table details: a plain table without index, rule, trigger nor policy
+                "SELECT 0 AS relchecks, 'r' AS relkind, false AS relhasindex, \
+                false AS relhasrules, false AS relhastriggers, false AS relrowsecurity, \
+                false AS relforcerowsecurity, false AS relhasoids, false AS relispartition, \
+                '' AS reloptions, 0 AS reltablespace, '' AS reloftype, 'p' AS relpersistence, \
+                'd' AS relreplident, NULL AS amname"
+                    .to_string()
+            }
+            "pg_attribute" => match self.table_of_oid(query.oid.as_deref()) {
+                Some(table) => format!(
+                    "SELECT column_name AS attname, data_type AS format_type, \
+                    NULL AS attrdef, is_nullable = 'NO' AS attnotnull, NULL AS attcollation, \
+                    '' AS attidentity, '' AS attgenerated, 'p' AS attstorage, \
+                    '' AS attcompression, NULL AS attstattarget, NULL AS description \
+                    FROM information_schema.columns \
+                    WHERE table_schema = 'public' AND table_name = {} \
+                    ORDER BY ordinal_position",
+                    sql_string(&table)
+                ),
+                None => EMPTY_QUERY.to_string(),
+            },
+            _ => EMPTY_QUERY.to_string(),
+        };
+        Some(rewritten)
+    }
+}
+
+/// The parts of a psql catalog query its rewriting depends on
+#[derive(Debug, Default, PartialEq)]
+struct CatalogQuery {
+    /// catalog table read first, `pg_class` for instance
+    relation: String,
+    /// aliases or names of the selected columns
+    columns: Vec<String>,
+    /// `c.relname OPERATOR(pg_catalog.~) '<pattern>'`
+    name_pattern: Option<String>,
+    /// `c.relkind IN (<kinds>)`
+    kinds: Vec<String>,
+    /// `a.attrelid = '<oid>'`
+    oid: Option<String>,
+}
+
+impl CatalogQuery {
+    fn parse(sql: &str) -> Option<Self> {
+        let statements = Parser::parse_sql(&PostgreSqlDialect {}, sql).ok()?;
+        let [Statement::Query(query)] = statements.as_slice() else {
+            return None;
+        };
+        let SetExpr::Select(select) = query.body.as_ref() else {
+            return None;
+        };
+        let TableFactor::Table { name, .. } = &select.from.first()?.relation else {
+            return None;
+        };
+        let columns = select
+            .projection
+            .iter()
+            .filter_map(|item| match item {
+                SelectItem::ExprWithAlias { alias, .. } => Some(alias.value.clone()),
+                SelectItem::UnnamedExpr(expr) => column_name(expr).map(str::to_string),
+                _ => None,
+            })
+            .collect();
+        let mut catalog = CatalogQuery {
+            relation: name.0.last()?.value.clone(),
+            columns,
+            ..Default::default()
+        };
+        if let Some(selection) = &select.selection {
+            catalog.add_filter(selection);
+        }
+        Some(catalog)
+    }
+
+    /// Record the conditions of a where clause the rewriting uses
+    fn add_filter(&mut self, expr: &Expr) {
+        match expr {
+            Expr::BinaryOp {
+                left,
+                op: BinaryOperator::And,
+                right,
+            } => {
+                self.add_filter(left);
+                self.add_filter(right);
+            }
+            Expr::Nested(expr) => self.add_filter(expr),
+            Expr::BinaryOp { left, op, right } => match (column_name(left), op) {
+                (
+                    Some("relname"),
+                    BinaryOperator::PGCustomBinaryOperator(_) | BinaryOperator::PGRegexMatch,
+                ) => self.name_pattern = string_value(right),
+                (Some("attrelid"), BinaryOperator::Eq) => self.oid = string_value(right),
+                _ => {}
+            },
+            Expr::InList {
+                expr,
+                list,
+                negated: false,
+            } if column_name(expr) == Some("relkind") => {
+                self.kinds = list.iter().filter_map(string_value).collect();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn column_name(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Identifier(ident) => Some(&ident.value),
+        Expr::CompoundIdentifier(idents) => idents.last().map(|ident| ident.value.as_str()),
+        _ => None,
+    }
+}
+
+fn string_value(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Value(Value::SingleQuotedString(value)) => Some(value.clone()),
+        Expr::Collate { expr, .. } | Expr::Nested(expr) => string_value(expr),
+        _ => None,
+    }
+}
+
+/// Sql literal of `value`, quotes escaped
+fn sql_string(value: &str) -> String {
+    Value::SingleQuotedString(value.to_string()).to_string()
+}
+
+#[async_trait]
+impl SimpleQueryHandler for PgServer {
+    async fn do_query<'a, C>(
+        &self,
+        _client: &mut C,
+        query: &'a str,
+    ) -> PgWireResult<Vec<Response<'a>>>
+    where
+        C: ClientInfo + Unpin + Send + Sync,
+    {
+        let statements = match self.catalog_query(query) {
+            Some(rewritten) => vec![rewritten],
+            None => split_statements(query)?,
+        };
+        let mut responses = Vec::with_capacity(statements.len());
+        for sql in statements {
+            responses.push(self.execute(&sql, vec![], &Format::UnifiedText).await?);
+        }
+        Ok(responses)
+    }
+}
+
+#[async_trait]
+impl ExtendedQueryHandler for PgServer {
+    type Statement = String;
+    type QueryParser = NoopQueryParser;
+
+    fn query_parser(&self) -> Arc<Self::QueryParser> {
+        self.query_parser.clone()
+    }
+
+    // every row, `on_execute` handles the executes limited in rows
+    async fn do_query<'a, C>(
+        &self,
+        _client: &mut C,
+        portal: &'a Portal<Self::Statement>,
+        _max_rows: usize,
+    ) -> PgWireResult<Response<'a>>
+    where
+        C: ClientInfo + Unpin + Send + Sync,
+    {
+        self.execute_portal(portal).await
+    }
+
+    /// Send at most `max_rows` rows, suspending the portal until the next
+    /// execute when more are left
+    async fn on_execute<C>(&self, client: &mut C, message: Execute) -> PgWireResult<()>
+    where
+        C: ClientInfo + ClientPortalStore + Sink<PgWireBackendMessage> + Unpin + Send + Sync,
+        C::PortalStore: PortalStore<Statement = Self::Statement>,
+        C::Error: Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        let name = message.name.as_deref().unwrap_or(DEFAULT_NAME);
+        let portal = client
+            .portal_store()
+            .get_portal(name)
+            .ok_or_else(|| PgWireError::PortalNotFound(name.to_owned()))?;
+        let key = (client.socket_addr(), name.to_owned());
+        let suspended = self
+            .cursors
+            .lock()
+            .unwrap()
+            .remove(&key)
+            .filter(|cursor| Arc::ptr_eq(&cursor.portal, &portal));
+        let mut cursor = match suspended {
+            Some(cursor) => cursor,
+            None => match self.execute_portal(&portal).await? {
+                Response::Query(results) => Cursor {
+                    portal,
+                    rows: results.data_rows(),
+                },
+                Response::Execution(tag) => return send_execution_response(client, tag).await,
+                Response::EmptyQuery => {
+                    client
+                        .feed(PgWireBackendMessage::EmptyQueryResponse(
+                            EmptyQueryResponse::new(),
+                        ))
+                        .await?;
+                    return Ok(());
+                }
+                Response::Error(err) => {
+                    client
+                        .send(PgWireBackendMessage::ErrorResponse((*err).into()))
+                        .await?;
+                    return Ok(());
+                }
+            },
+        };
+        // 0 asks for every row
+        let max_rows = usize::try_from(message.max_rows).unwrap_or_default();
+        let mut rows = 0;
+        while max_rows == 0 || rows < max_rows {
+            let Some(row) = cursor.rows.next().await else {
+                let tag = Tag::new("SELECT").with_rows(rows);
+                return send_execution_response(client, tag).await;
+            };
+            client.feed(PgWireBackendMessage::DataRow(row?)).await?;
+            rows += 1;
+        }
+        client
+            .send(PgWireBackendMessage::PortalSuspended(PortalSuspended::new()))
+            .await?;
+        self.cursors.lock().unwrap().insert(key, cursor);
+        Ok(())
+    }
+
+    async fn do_describe_statement<C>(
+        &self,
+        _client: &mut C,
+        stmt: &StoredStatement<Self::Statement>,
+    ) -> PgWireResult<DescribeStatementResponse>
+    where
+        C: ClientInfo + Unpin + Send + Sync,
+    {
+        if ignored_statement(&stmt.statement).is_some() {
+            return Ok(DescribeStatementResponse::new(vec![], vec![]));
+        }
+        let sql = self
+            .catalog_query(&stmt.statement)
+            .unwrap_or_else(|| stmt.statement.clone());
+        let plan = self.plan(&sql).await?;
+        let param_types = self
+            .parameter_types(stmt)
+            .await?
+            .into_iter()
+            .map(|(pg_type, _)| pg_type)
+            .collect();
+        let fields = field_infos(plan.schema().as_arrow(), &Format::UnifiedText)?;
+        Ok(DescribeStatementResponse::new(param_types, fields))
+    }
+
+    async fn do_describe_portal<C>(
+        &self,
+        _client: &mut C,
+        portal: &Portal<Self::Statement>,
+    ) -> PgWireResult<DescribePortalResponse>
+    where
+        C: ClientInfo + Unpin + Send + Sync,
+    {
+        if ignored_statement(&portal.statement.statement).is_some() {
+            return Ok(DescribePortalResponse::new(vec![]));
+        }
+        let sql = self
+            .catalog_query(&portal.statement.statement)
+            .unwrap_or_else(|| portal.statement.statement.clone());
+        let plan = self.plan(&sql).await?;
+        let fields = field_infos(plan.schema().as_arrow(), &portal.result_column_format)?;
+        Ok(DescribePortalResponse::new(fields))
+    }
+}
+
+/// Accepts every connection and advertises a recent server version, psql picks
+/// its catalog queries from it
+struct PgStartupHandler;
+
+#[async_trait]
+impl StartupHandler for PgStartupHandler {
+    async fn on_startup<C>(
+        &self,
+        client: &mut C,
+        message: PgWireFrontendMessage,
+    ) -> PgWireResult<()>
+    where
+        C: ClientInfo + Sink<PgWireBackendMessage> + Unpin + Send,
+        C::Error: std::fmt::Debug,
+        PgWireError: From<<C as Sink<PgWireBackendMessage>>::Error>,
+    {
+        if let PgWireFrontendMessage::Startup(ref startup) = message {
+            save_startup_parameters_to_metadata(client, startup);
+            let mut parameters = DefaultServerParameterProvider::default();
+            parameters.server_version = SERVER_VERSION.to_string();
+            finish_authentication(client, &parameters).await;
+        }
+        Ok(())
+    }
+}
+
+pub async fn serve(tblctx: Arc<TableContext>, host: &str, port: u16) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind((host, port)).await?;
+    info!("postgres endpoint listening on {}", listener.local_addr()?);
+    let startup = Arc::new(PgStartupHandler);
+    let handler = Arc::new(PgServer::new(tblctx));
+    loop {
+        let (socket, addr) = tokio::select! {
+            incoming = listener.accept() => incoming?,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
+        debug!("connection from {}", addr);
+        let startup = startup.clone();
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            if let Err(err) =
+                process_socket(socket, None, startup, handler.clone(), handler.clone()).await
+            {
+                warn!("connection from {} fails: {}", addr, err);
+            }
+            handler.close_cursors(addr);
+        });
+    }
+}
+
+fn user_error<E: std::fmt::Display>(err: E) -> PgWireError {
+    PgWireError::UserError(Box::new(ErrorInfo::new(
+        "ERROR".to_owned(),
+        "XX000".to_owned(),
+        err.to_string(),
+    )))
+}
+
+fn ignored_statement(sql: &str) -> Option<String> {
+    let keyword = sql.split_whitespace().next()?.trim_end_matches(';');
+    let keyword = keyword.to_uppercase();
+    // datafusion options would change the session of every client, they are
+    // rejected when planned
+    if keyword == "SET" && sql.to_lowercase().contains("datafusion.") {
+        return None;
+    }
+    IGNORED_STATEMENTS
+        .contains(&keyword.as_str())
+        .then_some(keyword)
+}
+
+fn split_statements(query: &str) -> PgWireResult<Vec<String>> {
+    if query
+        .split(';')
+        .filter(|s| !s.trim().is_empty())
+        .take(2)
+        .count()
+        < 2
+    {
+        return Ok(vec![query.to_string()]);
+    }
+    let statements = DFParser::parse_sql(query)
+        .map_err(|e| user_error(DataFusionError::from(e)))?
+        .into_iter()
+        .map(|s| s.to_string())
+        .collect();
+    Ok(statements)
+}
+
+fn binary_parameter(
+    portal: &Portal<String>,
+    idx: usize,
+    pg_type: &Type,
+) -> PgWireResult<ScalarValue> {
+    let value = match *pg_type {
+        Type::BOOL => ScalarValue::Boolean(portal.parameter::<bool>(idx, pg_type)?),
+        Type::INT2 => ScalarValue::Int16(portal.parameter::<i16>(idx, pg_type)?),
+        Type::INT4 => ScalarValue::Int32(portal.parameter::<i32>(idx, pg_type)?),
+        Type::INT8 => ScalarValue::Int64(portal.parameter::<i64>(idx, pg_type)?),
+        Type::FLOAT4 => ScalarValue::Float32(portal.parameter::<f32>(idx, pg_type)?),
+        Type::FLOAT8 => ScalarValue::Float64(portal.parameter::<f64>(idx, pg_type)?),
+        Type::TEXT | Type::VARCHAR => ScalarValue::Utf8(portal.parameter::<String>(idx, pg_type)?),
+        Type::DATE => ScalarValue::Date32(
+            portal
+                .parameter::<NaiveDate>(idx, pg_type)?
+                .map(|d| (d - NaiveDate::default()).num_days() as i32),
+        ),
+        Type::TIMESTAMP => ScalarValue::TimestampMicrosecond(
+            portal
+                .parameter::<NaiveDateTime>(idx, pg_type)?
+                .map(|ts| ts.and_utc().timestamp_micros()),
+            None,
+        ),
+        _ => {
+            return Err(user_error(format!(
+                "Unsupported binary parameter type {}",
+                pg_type
+            )))
+        }
+    };
+    Ok(value)
+}
+
+/// Postgres type used to send an arrow type, nested and exotic types are sent as text
+pub fn pg_type(data_type: &DataType) -> Type {
+    match data_type {
+        DataType::Boolean => Type::BOOL,
+        DataType::Int8 | DataType::Int16 | DataType::UInt8 => Type::INT2,
+        DataType::Int32 | DataType::UInt16 => Type::INT4,
+        DataType::Int64 | DataType::UInt32 | DataType::UInt64 => Type::INT8,
+        DataType::Float16 | DataType::Float32 => Type::FLOAT4,
+        DataType::Float64 => Type::FLOAT8,
+        DataType::Utf8 | DataType::LargeUtf8 => Type::VARCHAR,
+        DataType::Binary | DataType::LargeBinary | DataType::FixedSizeBinary(_) => Type::BYTEA,
+        DataType::Date32 | DataType::Date64 => Type::DATE,
+        DataType::Time32(_) | DataType::Time64(_) => Type::TIME,
+        DataType::Timestamp(_, None) => Type::TIMESTAMP,
+        DataType::Timestamp(_, Some(_)) => Type::TIMESTAMPTZ,
+        DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => Type::NUMERIC,
+        _ => Type::TEXT,
+    }
+}
+
+fn field_infos(schema: &arrow::datatypes::Schema, format: &Format) -> PgWireResult<Vec<FieldInfo>> {
+    Ok(schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            FieldInfo::new(
+                field.name().clone(),
+                None,
+                None,
+                pg_type(field.data_type()),
+                format.format_for(idx),
+            )
+        })
+        .collect())
+}
+
+/// Cast a column to the arrow type matching its postgres type encoding
+fn normalize(array: &ArrayRef) -> PgWireResult<ArrayRef> {
+    let target = match array.data_type() {
+        DataType::Int8 | DataType::UInt8 => DataType::Int16,
+        DataType::UInt16 => DataType::Int32,
+        DataType::UInt32 | DataType::UInt64 => DataType::Int64,
+        DataType::Float16 => DataType::Float32,
+        DataType::LargeUtf8 | DataType::Null => DataType::Utf8,
+        DataType::LargeBinary | DataType::FixedSizeBinary(_) => DataType::Binary,
+        DataType::Date64 => DataType::Date32,
+        DataType::Time32(_) | DataType::Time64(_) => DataType::Time64(TimeUnit::Microsecond),
+        DataType::Timestamp(_, tz) => DataType::Timestamp(TimeUnit::Microsecond, tz.clone()),
+        _ => return Ok(array.clone()),
+    };
+    cast(array, &target).map_err(user_error)
+}
+
+/// Data rows of a query, encoded batch by batch as they are computed
+fn encode_stream(
+    batches: SendableRecordBatchStream,
+    fields: Arc<Vec<FieldInfo>>,
+) -> impl Stream<Item = PgWireResult<DataRow>> + Send {
+    batches.flat_map(move |batch| {
+        let rows = match batch
+            .map_err(user_error)
+            .and_then(|b| encode_batch(&b, &fields))
+        {
+            Ok(rows) => rows.into_iter().map(Ok).collect(),
+            Err(err) => vec![Err(err)],
+        };
+        stream::iter(rows)
+    })
+}
+
+fn encode_batch(batch: &RecordBatch, fields: &Arc<Vec<FieldInfo>>) -> PgWireResult<Vec<DataRow>> {
+    let columns = batch
+        .columns()
+        .iter()
+        .map(normalize)
+        .collect::<PgWireResult<Vec<_>>>()?;
+    let options = FormatOptions::default();
+    let mut rows = Vec::with_capacity(batch.num_rows());
+    let formatters = columns
+        .iter()
+        .map(|c| ArrayFormatter::try_new(c.as_ref(), &options))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(user_error)?;
+    for row in 0..batch.num_rows() {
+        let mut encoder = DataRowEncoder::new(fields.clone());
+        for (col, array) in columns.iter().enumerate() {
+            if array.is_null(row) {
+                encoder.encode_field(&None::<i16>)?;
+                continue;
+            }
+            match array.data_type() {
+                DataType::Boolean => encoder.encode_field(&array.as_boolean().value(row))?,
+                DataType::Int16 => {
+                    encoder.encode_field(&array.as_primitive::<Int16Type>().value(row))?
+                }
+                DataType::Int32 => {
+                    encoder.encode_field(&array.as_primitive::<Int32Type>().value(row))?
+                }
+                DataType::Int64 => {
+                    encoder.encode_field(&array.as_primitive::<Int64Type>().value(row))?
+                }
+                DataType::Float32 => {
+                    encoder.encode_field(&array.as_primitive::<Float32Type>().value(row))?
+                }
+                DataType::Float64 => {
+                    encoder.encode_field(&array.as_primitive::<Float64Type>().value(row))?
+                }
+                DataType::Utf8 => encoder.encode_field(&array.as_string::<i32>().value(row))?,
+                DataType::Binary => encoder.encode_field(&array.as_binary::<i32>().value(row))?,
+                DataType::Date32 => {
+                    encoder.encode_field(&array.as_primitive::<Date32Type>().value_as_date(row))?
+                }
+                DataType::Time64(_) => encoder.encode_field(
+                    &array
+                        .as_primitive::<Time64MicrosecondType>()
+                        .value_as_time(row),
+                )?,
+                DataType::Timestamp(_, None) => encoder.encode_field(
+                    &array
+                        .as_primitive::<TimestampMicrosecondType>()
+                        .value_as_datetime(row),
+                )?,
+                DataType::Timestamp(_, Some(_)) => encoder.encode_field(
+                    &array
+                        .as_primitive::<TimestampMicrosecondType>()
+                        .value_as_datetime(row)
+                        .map(|ts| ts.and_utc()),
+                )?,
+                DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => {
+                    encoder.encode_field(&Numeric(formatters[col].value(row).to_string()))?
+                }
+                _ => encoder.encode_field(&formatters[col].value(row).to_string())?,
+            }
+        }
+        rows.push(encoder.finish()?);
+    }
+    Ok(rows)
+}
+
+/// Decimal sent as a postgres numeric, from its text representation
+#[derive(Debug)]
+struct Numeric(String);
+
+impl ToSql for Numeric {
+    /// Base 10000 digits aligned on the decimal point, without the leading
+    /// and trailing zero ones
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        let (negative, value) = match self.0.strip_prefix('-') {
+            Some(value) => (true, value),
+            None => (false, self.0.as_str()),
+        };
+        let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+        let int = int.trim_start_matches('0');
+        let int_pad = (4 - int.len() % 4) % 4;
+        let frac_pad = (4 - frac.len() % 4) % 4;
+        let padded = format!(
+            "{}{}{}{}",
+            "0".repeat(int_pad),
+            int,
+            frac,
+            "0".repeat(frac_pad)
+        );
+        let mut digits = padded
+            .as_bytes()
+            .chunks(4)
+            .map(|chunk| {
+                std::str::from_utf8(chunk)?
+                    .parse::<i16>()
+                    .map_err(Into::into)
+            })
+            .collect::<Result<Vec<i16>, Box<dyn std::error::Error + Sync + Send>>>()?;
+        let mut weight = ((int.len() + int_pad) / 4) as i16 - 1;
+        let leading = digits.iter().take_while(|digit| **digit == 0).count();
+        digits.drain(..leading);
+        weight -= leading as i16;
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            weight = 0;
+        }
+        out.put_i16(digits.len() as i16);
+        out.put_i16(weight);
+        out.put_u16(if negative && !digits.is_empty() {
+            0x4000
+        } else {
+            0
+        });
+        out.put_u16(frac.len() as u16);
+        for digit in digits {
+            out.put_i16(digit);
+        }
+        Ok(IsNull::No)
+    }
+
+    accepts!(NUMERIC);
+
+    to_sql_checked!();
+}
+
+impl ToSqlText for Numeric {
+    fn to_sql_text(
+        &self,
+        _ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        out.put_slice(self.0.as_bytes());
+        Ok(IsNull::No)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::prelude::SessionContext;
+
+    use crate::table::Format;
+    use crate::table::TableSpec;
+
+    // sent by psql 15 for `\dt`
+    const LIST_TABLES: &str = r#"SELECT n.nspname as "Schema",
+  c.relname as "Name",
+  CASE c.relkind WHEN 'r' THEN 'table' WHEN 'v' THEN 'view' END as "Type",
+  pg_catalog.pg_get_userbyid(c.relowner) as "Owner"
+FROM pg_catalog.pg_class c
+     LEFT JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE c.relkind IN ('r','p','')
+      AND n.nspname <> 'pg_catalog'
+      AND n.nspname !~ '^pg_toast'
+  AND pg_catalog.pg_table_is_visible(c.oid)
+ORDER BY 1,2;"#;
+
+    // sent by psql 15 for `\d tbl`
+    const FIND_TABLE: &str = r#"SELECT c.oid,
+  n.nspname,
+  c.relname
+FROM pg_catalog.pg_class c
+     LEFT JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE c.relname OPERATOR(pg_catalog.~) '^(tbl)$' COLLATE pg_catalog.default
+  AND pg_catalog.pg_table_is_visible(c.oid)
+ORDER BY 2, 3;"#;
+
+    fn server() -> PgServer {
+        let dir = std::env::temp_dir();
+        PgServer::new(Arc::new(TableContext::new(vec![TableSpec::new(
+            "it's",
+            dir.to_str().unwrap(),
+        )
+        .unwrap()
+        .with_format(Format::Parquet)])))
+    }
+
+    #[test]
+    fn catalog_query_parses_psql_listing() {
+        let query = CatalogQuery::parse(LIST_TABLES).unwrap();
+        assert_eq!(query.relation, "pg_class");
+        assert_eq!(query.columns, ["Schema", "Name", "Type", "Owner"]);
+        assert_eq!(query.kinds, ["r", "p", ""]);
+        assert_eq!(query.name_pattern, None);
+    }
+
+    #[test]
+    fn catalog_query_parses_psql_lookup() {
+        let query = CatalogQuery::parse(FIND_TABLE).unwrap();
+        assert_eq!(query.relation, "pg_class");
+        assert_eq!(query.columns, ["oid", "nspname", "relname"]);
+        assert_eq!(query.name_pattern.as_deref(), Some("^(tbl)$"));
+    }
+
+    #[test]
+    fn catalog_query_escapes_literals() {
+        let server = server();
+        let rewritten = server
+            .catalog_query(&FIND_TABLE.replace("^(tbl)$", "^(it''s)$"))
+            .unwrap();
+        assert!(rewritten.contains("'it''s'"), "{}", rewritten);
+        assert!(rewritten.contains("'^(it''s)$'"), "{}", rewritten);
+        assert!(DFParser::parse_sql(&rewritten).is_ok());
+    }
+
+    #[tokio::test]
+    async fn plan_rejects_statements() {
+        let server = server();
+        for sql in [
+            "create table t as select 1",
+            "set datafusion.execution.batch_size = 1",
+        ] {
+            assert!(server.plan(sql).await.is_err(), "{}", sql);
+        }
+        assert!(server.plan("select 1").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn catalog_uses_registered_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.parquet");
+        SessionContext::new()
+            .sql(&format!(
+                "copy (select 1 as id, 'a' as name) to '{}' stored as parquet",
+                path.display()
+            ))
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let tblctx = TableContext::new(vec![TableSpec::new("MyTable", path.to_str().unwrap())
+            .unwrap()
+            .with_format(Format::Parquet)]);
+        tblctx.register_tables().await.unwrap();
+        let server = PgServer::new(Arc::new(tblctx));
+        assert_eq!(server.table_oids(), [(FIRST_OID, "mytable".to_string())]);
+        let columns = server
+            .catalog_query(&format!(
+                "SELECT a.attname FROM pg_catalog.pg_attribute a \
+                WHERE a.attrelid = '{}' AND a.attnum > 0",
+                FIRST_OID
+            ))
+            .unwrap();
+        let batches = server.tblctx.context().sql(&columns).await.unwrap();
+        assert_eq!(batches.count().await.unwrap(), 2);
+    }
+
+    fn numeric(text: &str) -> Vec<i16> {
+        let mut out = BytesMut::new();
+        Numeric(text.to_string())
+            .to_sql(&Type::NUMERIC, &mut out)
+            .unwrap();
+        out.chunks(2)
+            .map(|word| i16::from_be_bytes([word[0], word[1]]))
+            .collect()
+    }
+
+    #[test]
+    fn numeric_binary_digits() {
+        // digits, weight, sign, scale then base 10000 digits
+        assert_eq!(numeric("-1234.5600"), [2, 0, 0x4000, 4, 1234, 5600]);
+        assert_eq!(numeric("12345678.9"), [3, 1, 0, 1, 1234, 5678, 9000]);
+        assert_eq!(numeric("0.00001"), [1, -2, 0, 5, 1000]);
+        assert_eq!(numeric("100000000"), [1, 2, 0, 0, 1]);
+        assert_eq!(numeric("-0.00"), [0, 0, 0, 2]);
+    }
+}
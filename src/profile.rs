@@ -0,0 +1,668 @@
+use std::sync::Arc;
+
+use datafusion::arrow::array::{ArrayRef, Float64Array, StringArray, UInt64Array};
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::common::stats::Precision;
+use log::info;
+use serde_json::{json, Value};
+
+use crate::error::{AdtError, Result};
+use crate::table::TableContext;
+use crate::tui::type_name;
+
+/// Quantiles of the numeric values and string lengths
+const QUANTILES: [f64; 3] = [0.25, 0.5, 0.75];
+/// Blocks drawing the histograms, from empty to full
+const BARS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// What is computed for a column
+pub struct ProfileOptions {
+    /// count the distinct values exactly instead of estimating them
+    pub exact_distinct: bool,
+    /// number of most frequent values
+    pub top: usize,
+    /// number of histogram buckets
+    pub bins: usize,
+}
+
+/// Distribution of numeric values or string lengths
+pub struct Summary {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub stddev: Option<f64>,
+    /// approximate values at each of the `QUANTILES`
+    pub quantiles: Vec<(f64, Option<f64>)>,
+    /// counts of equal width buckets from `min` to `max`
+    pub histogram: Vec<u64>,
+}
+
+impl Summary {
+    fn to_json(&self) -> Value {
+        let quantiles: serde_json::Map<String, Value> = self
+            .quantiles
+            .iter()
+            .map(|(quantile, value)| (format!("p{}", quantile * 100.0), json!(value)))
+            .collect();
+        json!({
+            "min": self.min,
+            "max": self.max,
+            "mean": self.mean,
+            "stddev": self.stddev,
+            "quantiles": quantiles,
+            "histogram": self.histogram,
+        })
+    }
+
+    /// Histogram as a line of blocks, after its range
+    fn sparkline(&self) -> String {
+        let highest = self
+            .histogram
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or_default()
+            .max(1);
+        let bars: String = self
+            .histogram
+            .iter()
+            .map(|count| BARS[((count * 8).div_ceil(highest)) as usize])
+            .collect();
+        format!("{}..{} {}", self.min, self.max, bars)
+    }
+}
+
+/// Statistics of a column, the ones read from file statistics being partial
+pub struct ColumnProfile {
+    pub name: String,
+    pub data_type: DataType,
+    pub rows: u64,
+    pub nulls: Option<u64>,
+    pub distinct: Option<u64>,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    /// of the values of numeric columns
+    pub values: Option<Summary>,
+    /// of the lengths of string columns
+    pub lengths: Option<Summary>,
+    /// most frequent values along with their count
+    pub top: Vec<(String, u64)>,
+}
+
+impl ColumnProfile {
+    fn new(field: &Field, rows: u64) -> Self {
+        Self {
+            name: field.name().clone(),
+            data_type: field.data_type().clone(),
+            rows,
+            nulls: None,
+            distinct: None,
+            min: None,
+            max: None,
+            values: None,
+            lengths: None,
+            top: Vec::new(),
+        }
+    }
+
+    pub fn null_ratio(&self) -> Option<f64> {
+        let nulls = self.nulls?;
+        (self.rows > 0).then(|| nulls as f64 / self.rows as f64)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let top: Vec<Value> = self
+            .top
+            .iter()
+            .map(|(value, count)| json!({ "value": value, "count": count }))
+            .collect();
+        json!({
+            "column": self.name,
+            "type": type_name(&self.data_type),
+            "rows": self.rows,
+            "nulls": self.nulls,
+            "null_ratio": self.null_ratio(),
+            "distinct": self.distinct,
+            "min": self.min,
+            "max": self.max,
+            "values": self.values.as_ref().map(Summary::to_json),
+            "lengths": self.lengths.as_ref().map(Summary::to_json),
+            "top": top,
+        })
+    }
+}
+
+/// Which statistics apply to a column
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Numeric,
+    Text,
+    /// nested or binary values, only counted
+    Opaque,
+    Other,
+}
+
+impl Kind {
+    fn of(data_type: &DataType) -> Self {
+        match data_type {
+            data_type if data_type.is_numeric() => Kind::Numeric,
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => Kind::Text,
+            data_type if data_type.is_nested() => Kind::Opaque,
+            DataType::Null
+            | DataType::Binary
+            | DataType::LargeBinary
+            | DataType::BinaryView
+            | DataType::FixedSizeBinary(_) => Kind::Opaque,
+            _ => Kind::Other,
+        }
+    }
+
+    /// Expression of the summarized values of `column`
+    fn summarized(self, column: &str) -> Option<String> {
+        match self {
+            Kind::Numeric => Some(format!("cast({} as double)", column)),
+            Kind::Text => Some(format!("cast(character_length({}) as double)", column)),
+            Kind::Opaque | Kind::Other => None,
+        }
+    }
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Value of the single row result `batch`, cast to `data_type`
+fn result_value(batch: &RecordBatch, name: &str, data_type: &DataType) -> Result<ArrayRef> {
+    let Some(column) = batch.column_by_name(name) else {
+        return Err(AdtError::Table(format!(
+            "No {} column in the profile query results",
+            name
+        )));
+    };
+    Ok(cast(column, data_type)?)
+}
+
+fn result_f64(batch: &RecordBatch, name: &str) -> Result<Option<f64>> {
+    let array = result_value(batch, name, &DataType::Float64)?;
+    let values = array.as_any().downcast_ref::<Float64Array>();
+    Ok(values.and_then(|values| values.iter().next().flatten()))
+}
+
+fn result_u64(batch: &RecordBatch, name: &str) -> Result<Option<u64>> {
+    let array = result_value(batch, name, &DataType::UInt64)?;
+    let values = array.as_any().downcast_ref::<UInt64Array>();
+    Ok(values.and_then(|values| values.iter().next().flatten()))
+}
+
+fn result_string(batch: &RecordBatch, name: &str) -> Result<Option<String>> {
+    let array = result_value(batch, name, &DataType::Utf8)?;
+    let values = array.as_any().downcast_ref::<StringArray>();
+    Ok(values.and_then(|values| values.iter().next().flatten().map(String::from)))
+}
+
+async fn collect(tblctx: &TableContext, query: &str) -> Result<Vec<RecordBatch>> {
+    info!("profile query: {}", query);
+    let df = tblctx.context().sql(query).await?;
+    Ok(df.collect().await?)
+}
+
+/// Profile every column of the `table` registered in `tblctx` in three scans:
+/// the first one computes the counts and summaries of all the columns, the
+/// next ones their most frequent values and their histograms.
+pub async fn profile_table(
+    tblctx: &TableContext,
+    table: &str,
+    options: &ProfileOptions,
+) -> Result<Vec<ColumnProfile>> {
+    let schema = tblctx.context().table_provider(table).await?.schema();
+    let table = quote(table);
+    let mut exprs = vec!["count(*) as rows".to_string()];
+    for (index, field) in schema.fields().iter().enumerate() {
+        let column = quote(field.name());
+        let kind = Kind::of(field.data_type());
+        exprs.push(format!("count({}) as c{}_count", column, index));
+        if kind != Kind::Opaque {
+            let distinct = if options.exact_distinct {
+                format!("count(distinct {})", column)
+            } else {
+                format!("approx_distinct(cast({} as varchar))", column)
+            };
+            exprs.push(format!("{} as c{}_distinct", distinct, index));
+            exprs.push(format!("min({}) as c{}_min", column, index));
+            exprs.push(format!("max({}) as c{}_max", column, index));
+        }
+        if let Some(expr) = kind.summarized(&column) {
+            // the min and max of numeric values are the ones above, the
+            // planner would give both the same name
+            if kind == Kind::Text {
+                exprs.push(format!("min({}) as c{}_low", expr, index));
+                exprs.push(format!("max({}) as c{}_high", expr, index));
+            }
+            exprs.push(format!("avg({}) as c{}_mean", expr, index));
+            exprs.push(format!("stddev({}) as c{}_stddev", expr, index));
+            for (position, quantile) in QUANTILES.iter().enumerate() {
+                exprs.push(format!(
+                    "approx_percentile_cont({}, {}) as c{}_q{}",
+                    expr, quantile, index, position
+                ));
+            }
+        }
+    }
+    let query = format!("select {} from {}", exprs.join(", "), table);
+    let batches = collect(tblctx, &query).await?;
+    let Some(batch) = batches.iter().find(|batch| batch.num_rows() > 0) else {
+        return Err(AdtError::Table(
+            "No result for the profile query".to_string(),
+        ));
+    };
+    let rows = result_u64(batch, "rows")?.unwrap_or_default();
+
+    let mut profiles = Vec::with_capacity(schema.fields().len());
+    // values of the columns whose top values are counted, and buckets of the
+    // summarized ones, along with the index of their profile
+    let mut values = Vec::new();
+    let mut buckets = Vec::new();
+    for (index, field) in schema.fields().iter().enumerate() {
+        let column = quote(field.name());
+        let kind = Kind::of(field.data_type());
+        let mut profile = ColumnProfile::new(field, rows);
+        let count = result_u64(batch, &format!("c{}_count", index))?.unwrap_or_default();
+        profile.nulls = Some(rows - count);
+        if kind != Kind::Opaque {
+            // estimates may exceed the count of values
+            let distinct = result_u64(batch, &format!("c{}_distinct", index))?;
+            profile.distinct = distinct.map(|distinct| distinct.min(count));
+            profile.min = result_string(batch, &format!("c{}_min", index))?;
+            profile.max = result_string(batch, &format!("c{}_max", index))?;
+            values.push((index, format!("cast({} as varchar)", column)));
+        }
+        if let Some(expr) = kind.summarized(&column) {
+            let (low, high) = match kind {
+                Kind::Text => (
+                    result_f64(batch, &format!("c{}_low", index))?,
+                    result_f64(batch, &format!("c{}_high", index))?,
+                ),
+                _ => (
+                    profile.min.as_ref().and_then(|min| min.parse().ok()),
+                    profile.max.as_ref().and_then(|max| max.parse().ok()),
+                ),
+            };
+            let mean = result_f64(batch, &format!("c{}_mean", index))?;
+            if let (Some(min), Some(max), Some(mean)) = (low, high, mean) {
+                let mut quantiles = Vec::with_capacity(QUANTILES.len());
+                for (position, quantile) in QUANTILES.iter().enumerate() {
+                    let value = result_f64(batch, &format!("c{}_q{}", index, position))?;
+                    quantiles.push((*quantile, value));
+                }
+                let mut summary = Summary {
+                    min,
+                    max,
+                    mean,
+                    stddev: result_f64(batch, &format!("c{}_stddev", index))?,
+                    quantiles,
+                    histogram: Vec::new(),
+                };
+                if options.bins > 0 && max <= min {
+                    summary.histogram = vec![count];
+                } else if options.bins > 0 {
+                    let width = (max - min) / options.bins as f64;
+                    // bounds given as strings so that they are read back as doubles
+                    buckets.push((
+                        index,
+                        format!(
+                            "cast(floor(({expr} - cast('{min:?}' as double)) \
+                            / cast('{width:?}' as double)) as bigint)"
+                        ),
+                    ));
+                }
+                match kind {
+                    Kind::Text => profile.lengths = Some(summary),
+                    _ => profile.values = Some(summary),
+                }
+            }
+        }
+        profiles.push(profile);
+    }
+
+    if options.top > 0 && !values.is_empty() {
+        let exprs: Vec<String> = values.iter().map(|(_, expr)| expr.clone()).collect();
+        let query = format!(
+            "select * from ({}) where rank <= {} order by expr, rank",
+            grouped_counts_query(&table, &exprs, true),
+            options.top
+        );
+        for (expr, value, count) in grouped_counts(tblctx, &query).await? {
+            profiles[values[expr].0].top.push((value, count));
+        }
+    }
+    if !buckets.is_empty() {
+        let exprs: Vec<String> = buckets.iter().map(|(_, expr)| expr.clone()).collect();
+        let query = grouped_counts_query(&table, &exprs, false);
+        for (expr, bucket, count) in grouped_counts(tblctx, &query).await? {
+            let profile = &mut profiles[buckets[expr].0];
+            let Some(summary) = profile.values.as_mut().or(profile.lengths.as_mut()) else {
+                continue;
+            };
+            if summary.histogram.is_empty() {
+                summary.histogram = vec![0; options.bins];
+            }
+            // the max is in the last bucket
+            let bucket = bucket.parse::<usize>().unwrap_or_default();
+            summary.histogram[bucket.min(options.bins - 1)] += count;
+        }
+    }
+    Ok(profiles)
+}
+
+/// Query counting the non null values of each of `exprs` in a single scan of
+/// `table`, as (expr, value, count) rows where expr is the index of the
+/// expression and value is cast to a string. The values of an expression are
+/// ranked by descending count when `ranked`.
+fn grouped_counts_query(table: &str, exprs: &[String], ranked: bool) -> String {
+    let projection: Vec<String> = exprs
+        .iter()
+        .enumerate()
+        .map(|(index, expr)| format!("{} as g{}", expr, index))
+        .collect();
+    let sets: Vec<String> = (0..exprs.len()).map(|i| format!("(g{})", i)).collect();
+    // a grouping set leaves the other group columns null, and the null values
+    // of its own are skipped
+    let which: Vec<String> = (0..exprs.len())
+        .map(|i| format!("when g{i} is not null then {i}"))
+        .collect();
+    let values: Vec<String> = (0..exprs.len())
+        .map(|i| format!("cast(g{} as varchar)", i))
+        .collect();
+    let counts = format!(
+        "select * from (select case {} end as expr, coalesce({}) as value, count(*) as count \
+        from (select {} from {}) group by grouping sets ({})) where expr is not null",
+        which.join(" "),
+        values.join(", "),
+        projection.join(", "),
+        table,
+        sets.join(", ")
+    );
+    if ranked {
+        format!(
+            "select expr, value, count, row_number() over (partition by expr \
+            order by count desc, value) as rank from ({})",
+            counts
+        )
+    } else {
+        counts
+    }
+}
+
+/// Rows of a `grouped_counts_query`
+async fn grouped_counts(tblctx: &TableContext, query: &str) -> Result<Vec<(usize, String, u64)>> {
+    let mut counts = Vec::new();
+    for batch in collect(tblctx, query).await? {
+        let exprs = cast(batch.column(0), &DataType::UInt64)?;
+        let values = cast(batch.column(1), &DataType::Utf8)?;
+        let totals = cast(batch.column(2), &DataType::UInt64)?;
+        let (Some(exprs), Some(values), Some(totals)) = (
+            exprs.as_any().downcast_ref::<UInt64Array>(),
+            values.as_any().downcast_ref::<StringArray>(),
+            totals.as_any().downcast_ref::<UInt64Array>(),
+        ) else {
+            continue;
+        };
+        let rows = exprs.iter().zip(values.iter()).zip(totals.iter());
+        for ((expr, value), total) in rows {
+            if let (Some(expr), Some(value), Some(total)) = (expr, value, total) {
+                counts.push((expr as usize, value.to_string(), total));
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// Profile of the `table` registered in `tblctx` answered from its parquet
+/// footers or delta log: row and null counts, min and max only
+pub async fn profile_from_statistics(
+    tblctx: &TableContext,
+    table: &str,
+) -> Result<Vec<ColumnProfile>> {
+    let (schema, statistics) = tblctx.file_statistics(table).await?;
+    let Some(rows) = statistics.num_rows.get_value() else {
+        return Err(AdtError::Table(format!(
+            "The file statistics of table {} have no row count",
+            table
+        )));
+    };
+    let value = |precision: &Precision<_>| precision.get_value().map(|v| *v as u64);
+    let scalar = |precision: &Precision<datafusion::common::ScalarValue>| {
+        precision
+            .get_value()
+            .filter(|value| !value.is_null())
+            .map(|value| value.to_string())
+    };
+    let profiles = schema
+        .fields()
+        .iter()
+        .zip(statistics.column_statistics.iter())
+        .map(|(field, column)| {
+            let mut profile = ColumnProfile::new(field, *rows as u64);
+            profile.nulls = value(&column.null_count);
+            profile.distinct = value(&column.distinct_count);
+            profile.min = scalar(&column.min_value);
+            profile.max = scalar(&column.max_value);
+            profile
+        })
+        .collect();
+    Ok(profiles)
+}
+
+/// Build a record batch from column profiles for tabular display
+pub fn profiles_to_batch(profiles: &[ColumnProfile]) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("column", DataType::Utf8, false),
+        Field::new("type", DataType::Utf8, false),
+        Field::new("rows", DataType::UInt64, false),
+        Field::new("nulls", DataType::UInt64, true),
+        Field::new("null_ratio", DataType::Float64, true),
+        Field::new("distinct", DataType::UInt64, true),
+        Field::new("min", DataType::Utf8, true),
+        Field::new("max", DataType::Utf8, true),
+        Field::new("mean", DataType::Float64, true),
+        Field::new("stddev", DataType::Float64, true),
+        Field::new("p25", DataType::Float64, true),
+        Field::new("p50", DataType::Float64, true),
+        Field::new("p75", DataType::Float64, true),
+        Field::new("lengths", DataType::Utf8, true),
+        Field::new("histogram", DataType::Utf8, true),
+        Field::new("top", DataType::Utf8, true),
+    ]);
+    let quantile = |position: usize| {
+        Float64Array::from_iter(profiles.iter().map(|p| {
+            let values = p.values.as_ref()?;
+            values.quantiles.get(position).and_then(|(_, value)| *value)
+        }))
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            profiles.iter().map(|p| p.name.as_str()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            profiles.iter().map(|p| type_name(&p.data_type)),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            profiles.iter().map(|p| p.rows),
+        )),
+        Arc::new(UInt64Array::from_iter(profiles.iter().map(|p| p.nulls))),
+        Arc::new(Float64Array::from_iter(
+            profiles.iter().map(|p| p.null_ratio()),
+        )),
+        Arc::new(UInt64Array::from_iter(profiles.iter().map(|p| p.distinct))),
+        Arc::new(StringArray::from_iter(
+            profiles.iter().map(|p| p.min.clone()),
+        )),
+        Arc::new(StringArray::from_iter(
+            profiles.iter().map(|p| p.max.clone()),
+        )),
+        Arc::new(Float64Array::from_iter(
+            profiles.iter().map(|p| p.values.as_ref().map(|v| v.mean)),
+        )),
+        Arc::new(Float64Array::from_iter(
+            profiles
+                .iter()
+                .map(|p| p.values.as_ref().and_then(|v| v.stddev)),
+        )),
+        Arc::new(quantile(0)),
+        Arc::new(quantile(1)),
+        Arc::new(quantile(2)),
+        Arc::new(StringArray::from_iter(profiles.iter().map(|p| {
+            p.lengths
+                .as_ref()
+                .map(|l| format!("{}..{} mean {:.1}", l.min, l.max, l.mean))
+        }))),
+        Arc::new(StringArray::from_iter(profiles.iter().map(|p| {
+            p.values
+                .as_ref()
+                .or(p.lengths.as_ref())
+                .filter(|summary| !summary.histogram.is_empty())
+                .map(Summary::sparkline)
+        }))),
+        Arc::new(StringArray::from_iter(profiles.iter().map(|p| {
+            let top: Vec<String> = p
+                .top
+                .iter()
+                .map(|(value, count)| format!("{} ({})", value, count))
+                .collect();
+            (!top.is_empty()).then(|| top.join(", "))
+        }))),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::Int64Array;
+    use datafusion::parquet::arrow::ArrowWriter;
+
+    use crate::table::{Format, TableSpec};
+
+    const OPTIONS: ProfileOptions = ProfileOptions {
+        exact_distinct: true,
+        top: 2,
+        bins: 4,
+    };
+
+    fn batch() -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("constant", DataType::Int64, false),
+            Field::new("empty", DataType::Int64, true),
+        ]);
+        let names = ["b", "a", "b", "c", "a", "b", "d", "ab", "", "abc"];
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int64Array::from_iter_values(1..=10)),
+                Arc::new(StringArray::from_iter(
+                    names.iter().map(|name| (!name.is_empty()).then_some(*name)),
+                )),
+                Arc::new(Int64Array::from(vec![7; 10])),
+                Arc::new(Int64Array::from(vec![None; 10])),
+            ],
+        )
+        .unwrap()
+    }
+
+    async fn profiles(batch: RecordBatch, options: &ProfileOptions) -> Vec<ColumnProfile> {
+        let tblctx = TableContext::new(vec![]);
+        tblctx.context().register_batch("tbl", batch).unwrap();
+        profile_table(&tblctx, "tbl", options).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn profile_counts_and_top_values() {
+        let profiles = profiles(batch(), &OPTIONS).await;
+        let names: Vec<&str> = profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["id", "name", "constant", "empty"]);
+        let name = &profiles[1];
+        assert_eq!(name.rows, 10);
+        assert_eq!(name.nulls, Some(1));
+        assert_eq!(name.distinct, Some(6));
+        assert_eq!(name.min.as_deref(), Some("a"));
+        assert_eq!(name.max.as_deref(), Some("d"));
+        // ties in value order, nulls not counted
+        let top = [("b".to_string(), 3), ("a".to_string(), 2)];
+        assert_eq!(name.top, top);
+        let lengths = name.lengths.as_ref().unwrap();
+        assert_eq!((lengths.min, lengths.max), (1.0, 3.0));
+        assert_eq!(lengths.histogram.iter().sum::<u64>(), 9);
+        assert_eq!(profiles[0].top.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn profile_histogram_buckets() {
+        let profiles = profiles(batch(), &OPTIONS).await;
+        // 1..=10 in buckets of 2.25, the max in the last one
+        let id = profiles[0].values.as_ref().unwrap();
+        assert_eq!((id.min, id.max, id.mean), (1.0, 10.0, 5.5));
+        assert_eq!(id.histogram, [3, 2, 2, 3]);
+        // a single bucket of every value
+        let constant = profiles[2].values.as_ref().unwrap();
+        assert_eq!((constant.min, constant.max), (7.0, 7.0));
+        assert_eq!(constant.histogram, [10]);
+        assert_eq!(profiles[2].top, [("7".to_string(), 10)]);
+        // nothing summarized without values
+        let empty = &profiles[3];
+        assert_eq!(empty.nulls, Some(10));
+        assert_eq!(empty.distinct, Some(0));
+        assert!(empty.values.is_none() && empty.top.is_empty());
+    }
+
+    #[tokio::test]
+    async fn profile_single_row() {
+        let profiles = profiles(batch().slice(0, 1), &OPTIONS).await;
+        let id = profiles[0].values.as_ref().unwrap();
+        assert_eq!(id.histogram, [1]);
+        assert_eq!(id.stddev, None);
+        assert_eq!(profiles[1].top, [("b".to_string(), 1)]);
+    }
+
+    #[tokio::test]
+    async fn profile_estimated_distinct() {
+        let options = ProfileOptions {
+            exact_distinct: false,
+            top: 0,
+            bins: 0,
+        };
+        let profiles = profiles(batch(), &options).await;
+        // estimates are exact on so few values
+        let distinct: Vec<Option<u64>> = profiles.iter().map(|p| p.distinct).collect();
+        assert_eq!(distinct, [Some(10), Some(6), Some(1), Some(0)]);
+        assert!(profiles.iter().all(|p| p.top.is_empty()));
+        assert!(profiles[0].values.as_ref().unwrap().histogram.is_empty());
+    }
+
+    #[tokio::test]
+    async fn profile_parquet_statistics() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.parquet");
+        let batch = batch();
+        let mut writer =
+            ArrowWriter::try_new(std::fs::File::create(&path).unwrap(), batch.schema(), None)
+                .unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        let spec = TableSpec::new("tbl", path.to_str().unwrap())
+            .unwrap()
+            .with_format(Format::Parquet);
+        let tblctx = TableContext::new(vec![spec]);
+        tblctx.register_tables().await.unwrap();
+        let profiles = profile_from_statistics(&tblctx, "tbl").await.unwrap();
+        assert!(profiles.iter().all(|p| p.rows == 10));
+        let nulls: Vec<Option<u64>> = profiles.iter().map(|p| p.nulls).collect();
+        assert_eq!(nulls, [Some(0), Some(1), Some(0), Some(10)]);
+        assert_eq!(profiles[0].min.as_deref(), Some("1"));
+        assert_eq!(profiles[0].max.as_deref(), Some("10"));
+        assert_eq!(profiles[3].min, None);
+    }
+}
@@ -0,0 +1,267 @@
+use std::sync::Arc;
+
+use arrow::csv::WriterBuilder as CsvWriterBuilder;
+use arrow::datatypes::SchemaRef;
+use arrow::ipc::writer::StreamWriter;
+use arrow::json::{ArrayWriter, LineDelimitedWriter};
+use arrow::record_batch::RecordBatch;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use datafusion::prelude::DataFrame;
+use log::{info, warn};
+use serde_json::json;
+
+use crate::error::{AdtError, Result};
+use crate::table::TableContext;
+
+/// Result encodings negotiated through the `Accept` header
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResultFormat {
+    Json,
+    NdJson,
+    Csv,
+    Arrow,
+}
+
+impl ResultFormat {
+    /// Pick the first supported media type of an `Accept` header value,
+    /// defaulting to json when the header is missing or accepts anything
+    pub fn from_accept(accept: Option<&str>) -> Option<Self> {
+        let Some(accept) = accept else {
+            return Some(ResultFormat::Json);
+        };
+        accept
+            .split(',')
+            .map(|media| media.split(';').next().unwrap_or_default().trim())
+            .find_map(|media| match media {
+                "application/json" | "*/*" | "application/*" => Some(ResultFormat::Json),
+                "application/x-ndjson" | "application/jsonlines" => Some(ResultFormat::NdJson),
+                "text/csv" | "text/*" => Some(ResultFormat::Csv),
+                "application/vnd.apache.arrow.stream" => Some(ResultFormat::Arrow),
+                _ => None,
+            })
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ResultFormat::Json => "application/json",
+            ResultFormat::NdJson => "application/x-ndjson",
+            ResultFormat::Csv => "text/csv",
+            ResultFormat::Arrow => "application/vnd.apache.arrow.stream",
+        }
+    }
+
+    pub fn encode(&self, schema: SchemaRef, batches: &[RecordBatch]) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        match self {
+            ResultFormat::Json => {
+                if batches.iter().all(|b| b.num_rows() == 0) {
+                    buf.extend_from_slice(b"[]");
+                } else {
+                    let mut writer = ArrayWriter::new(&mut buf);
+                    for batch in batches {
+                        writer.write(batch)?;
+                    }
+                    writer.finish()?;
+                }
+            }
+            ResultFormat::NdJson => {
+                let mut writer = LineDelimitedWriter::new(&mut buf);
+                for batch in batches {
+                    writer.write(batch)?;
+                }
+                writer.finish()?;
+            }
+            ResultFormat::Csv => {
+                let mut writer = CsvWriterBuilder::new().with_header(true).build(&mut buf);
+                for batch in batches {
+                    writer.write(batch)?;
+                }
+            }
+            ResultFormat::Arrow => {
+                let mut writer = StreamWriter::try_new(&mut buf, &schema)?;
+                for batch in batches {
+                    writer.write(batch)?;
+                }
+                writer.finish()?;
+            }
+        }
+        Ok(buf)
+    }
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<AdtError> for ApiError {
+    fn from(err: AdtError) -> Self {
+        let status = match err {
+            AdtError::Planning(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError(status, err.to_string())
+    }
+}
+
+type ApiResult = std::result::Result<Response, ApiError>;
+
+pub fn router(tblctx: Arc<TableContext>) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/tables", get(list_tables))
+        .route("/tables/:name/schema", get(table_schema))
+        .route("/query", post(query))
+        .with_state(tblctx)
+}
+
+pub async fn serve(tblctx: Arc<TableContext>, host: &str, port: u16) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind((host, port)).await?;
+    info!("listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, router(tblctx))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+async fn health() -> Json<serde_json::Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+async fn list_tables(State(tblctx): State<Arc<TableContext>>) -> Json<serde_json::Value> {
+    let tables: Vec<_> = tblctx
+        .tables()
+        .iter()
+        .map(|t| {
+            json!({
+                "name": t.name(),
+                "path": t.path().as_str(),
+                "format": t.format().map(|f| f.name()),
+            })
+        })
+        .collect();
+    Json(json!(tables))
+}
+
+async fn table_schema(
+    State(tblctx): State<Arc<TableContext>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult {
+    let format = negotiate(&headers)?;
+    if !tblctx.tables().iter().any(|t| t.name() == name) {
+        return Err(ApiError(
+            StatusCode::NOT_FOUND,
+            format!("Unknown table {}", name),
+        ));
+    }
+    let df = tblctx.table_schema(&name).await?;
+    respond(df, format).await
+}
+
+async fn query(
+    State(tblctx): State<Arc<TableContext>>,
+    headers: HeaderMap,
+    sql: String,
+) -> ApiResult {
+    let format = negotiate(&headers)?;
+    info!("query: {}", sql);
+    let df = tblctx.read_only_query(&sql).await?;
+    respond(df, format).await
+}
+
+fn negotiate(headers: &HeaderMap) -> std::result::Result<ResultFormat, ApiError> {
+    let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok());
+    ResultFormat::from_accept(accept).ok_or_else(|| {
+        ApiError(
+            StatusCode::NOT_ACCEPTABLE,
+            "Supported formats: application/json, application/x-ndjson, text/csv, \
+            application/vnd.apache.arrow.stream"
+                .to_string(),
+        )
+    })
+}
+
+async fn respond(df: DataFrame, format: ResultFormat) -> ApiResult {
+    let schema = df.schema().inner().clone();
+    let batches = df.collect().await.map_err(AdtError::from)?;
+    let body = format.encode(schema, &batches).map_err(|err| {
+        warn!("result encoding fails: {}", err);
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+    })?;
+    Ok(([(header::CONTENT_TYPE, format.content_type())], body).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn post_query(sql: &str, accept: Option<&str>) -> (StatusCode, String) {
+        let mut request = Request::post("/query");
+        if let Some(accept) = accept {
+            request = request.header(header::ACCEPT, accept);
+        }
+        let response = router(Arc::new(TableContext::new(vec![])))
+            .oneshot(request.body(Body::from(sql.to_string())).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn query_runs_selects() {
+        let (status, body) = post_query("select 1 as a", Some("text/csv")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "a\n1\n");
+    }
+
+    #[tokio::test]
+    async fn query_rejects_other_statements() {
+        for sql in [
+            "create table t as select 1",
+            "insert into t values (1)",
+            "set datafusion.execution.batch_size = 1",
+        ] {
+            let (status, body) = post_query(sql, None).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}: {}", sql, body);
+        }
+    }
+
+    #[tokio::test]
+    async fn query_rejects_unsupported_accept() {
+        let (status, _) = post_query("select 1", Some("image/png")).await;
+        assert_eq!(status, StatusCode::NOT_ACCEPTABLE);
+    }
+
+    #[test]
+    fn result_format_from_accept() {
+        let cases = [
+            (None, Some(ResultFormat::Json)),
+            (Some("*/*"), Some(ResultFormat::Json)),
+            (Some("application/x-ndjson"), Some(ResultFormat::NdJson)),
+            (Some("text/csv; charset=utf-8"), Some(ResultFormat::Csv)),
+            (
+                Some("image/png, application/vnd.apache.arrow.stream"),
+                Some(ResultFormat::Arrow),
+            ),
+            (Some("image/png"), None),
+        ];
+        for (accept, expected) in cases {
+            assert_eq!(ResultFormat::from_accept(accept), expected, "{:?}", accept);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeSet;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use arrow::array::{Array, StringArray};
+use arrow::util::pretty::pretty_format_batches;
+use log::warn;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::{Context, Editor, Helper, Highlighter, Hinter, Validator};
+
+use crate::context::SQLContext;
+use crate::error::{AdtError, Result};
+use crate::table::{TableContext, TableSpec};
+
+const HISTORY_FILE: &str = ".adt_history";
+const HELP: &str = "\\d           list tables
+\\d <table>   describe table
+\\timing      toggle query timing
+\\o [file]    send results to file, or back to stdout without file
+\\?           show this help
+\\q           quit";
+
+/// Completes table and column names of the session
+#[derive(Helper, Hinter, Highlighter, Validator, Default)]
+struct ShellHelper {
+    names: BTreeSet<String>,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map_or(0, |i| i + 1);
+        let word = line[start..pos].to_lowercase();
+        if word.is_empty() {
+            return Ok((start, vec![]));
+        }
+        let candidates = self
+            .names
+            .iter()
+            .filter(|name| name.to_lowercase().starts_with(&word))
+            .map(|name| Pair {
+                display: name.clone(),
+                replacement: name.clone(),
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+/// Interactive sql session on top of a single `SQLContext`
+pub struct Shell {
+    ctx: SQLContext,
+    timing: bool,
+    output: Option<File>,
+}
+
+impl Shell {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            ctx: SQLContext::new()?,
+            timing: false,
+            output: None,
+        })
+    }
+
+    /// Register tables in the session before it starts
+    pub async fn register_tables(&self, specs: Vec<TableSpec>) -> Result<()> {
+        let tblctx = TableContext::with_context(self.ctx.context().clone(), specs);
+        tblctx.register_tables().await?;
+        Ok(())
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        let mut rl: Editor<ShellHelper, DefaultHistory> = Editor::new()?;
+        rl.set_helper(Some(ShellHelper::default()));
+        let history = history_path();
+        if let Some(path) = &history {
+            let _ = rl.load_history(path);
+        }
+        self.refresh_completion(&mut rl).await;
+
+        // the history is kept however the session ends
+        let res = self.read_eval(&mut rl).await;
+        if let Some(path) = &history {
+            if let Err(err) = rl.save_history(path) {
+                warn!("Unable to save shell history: {}", err);
+            }
+        }
+        res
+    }
+
+    /// Read and run statements until the session ends
+    async fn read_eval(&mut self, rl: &mut Editor<ShellHelper, DefaultHistory>) -> Result<()> {
+        let mut query = String::new();
+        loop {
+            let prompt = if query.is_empty() { "adt> " } else { "  -> " };
+            let line = match rl.readline(prompt) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => {
+                    query.clear();
+                    continue;
+                }
+                Err(ReadlineError::Eof) => return Ok(()),
+                Err(err) => return Err(err.into()),
+            };
+            let line = line.trim_end();
+            if query.is_empty() && line.starts_with('\\') {
+                rl.add_history_entry(line)?;
+                if !self.meta_command(line).await {
+                    return Ok(());
+                }
+                continue;
+            }
+            if query.is_empty() && line.trim().is_empty() {
+                continue;
+            }
+            query.push_str(line);
+            if line.ends_with(';') {
+                rl.add_history_entry(query.as_str())?;
+                self.run_query(&query).await;
+                self.refresh_completion(rl).await;
+                query.clear();
+            } else {
+                query.push('\n');
+            }
+        }
+    }
+
+    /// Handle a backslash command, returns false when the session must end
+    async fn meta_command(&mut self, line: &str) -> bool {
+        let mut parts = line.split_whitespace();
+        let cmd = parts.next().unwrap_or_default();
+        let arg = parts.next();
+        match (cmd, arg) {
+            ("\\q", _) => return false,
+            ("\\d", None) => {
+                self.run_query(
+                    "select table_catalog, table_schema, table_name, table_type \
+                    from information_schema.tables \
+                    where table_schema != 'information_schema' order by table_name",
+                )
+                .await
+            }
+            ("\\d", Some(table)) => {
+                self.run_query(format!("show columns from {}", table).as_str())
+                    .await
+            }
+            ("\\timing", _) => {
+                self.timing = !self.timing;
+                println!("Timing is {}.", if self.timing { "on" } else { "off" });
+            }
+            ("\\o", None) => self.output = None,
+            ("\\o", Some(path)) => match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => self.output = Some(file),
+                Err(err) => eprintln!("Unable to open {}: {}", path, err),
+            },
+            ("\\?", _) => println!("{}", HELP),
+            _ => eprintln!("Unknown command {}, try \\?", cmd),
+        }
+        true
+    }
+
+    async fn run_query(&mut self, query: &str) {
+        let req_time = Instant::now();
+        let res = match self.ctx.sql(query).await {
+            Ok(df) => df.collect().await.map_err(AdtError::from),
+            Err(err) => Err(err),
+        };
+        let records = match res {
+            Ok(records) => records,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return;
+            }
+        };
+        let req_time_elapsed = req_time.elapsed();
+        let text = match pretty_format_batches(&records) {
+            Ok(text) => text.to_string(),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return;
+            }
+        };
+        match self.output.as_mut() {
+            Some(file) => {
+                if let Err(err) = writeln!(file, "{}", text) {
+                    eprintln!("Unable to write output: {}", err);
+                }
+            }
+            None => println!("{}", text),
+        }
+        if self.timing {
+            println!("Time: {:.2?}", req_time_elapsed);
+        }
+    }
+
+    /// Reload completion candidates from information_schema
+    async fn refresh_completion(&self, rl: &mut Editor<ShellHelper, DefaultHistory>) {
+        let query = "select table_name, column_name from information_schema.columns \
+            where table_schema != 'information_schema'";
+        let records = match self.ctx.sql(query).await {
+            Ok(df) => df.collect().await.unwrap_or_default(),
+            Err(_) => return,
+        };
+        let mut names = BTreeSet::new();
+        for batch in records.iter() {
+            for col in batch.columns() {
+                if let Some(values) = col.as_any().downcast_ref::<StringArray>() {
+                    names.extend(values.iter().flatten().map(String::from));
+                }
+            }
+        }
+        if let Some(helper) = rl.helper_mut() {
+            helper.names = names;
+        }
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
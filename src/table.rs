@@ -1,96 +1,445 @@
-use anyhow::Result;
+use chrono::DateTime;
 use datafusion::arrow::datatypes::DataType;
+use datafusion::arrow::datatypes::{Field, Schema, SchemaRef};
+use datafusion::arrow::ipc::convert::fb_to_schema;
+use datafusion::arrow::ipc::reader::StreamReader;
+use datafusion::arrow::ipc::root_as_footer;
+use datafusion::common::{Statistics, TableReference};
+use datafusion::datasource::file_format::arrow::ArrowFormat;
+use datafusion::datasource::file_format::avro::AvroFormat;
+use datafusion::datasource::file_format::csv::CsvFormat;
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use datafusion::datasource::file_format::json::JsonFormat;
 use datafusion::datasource::file_format::parquet::ParquetFormat;
+use datafusion::datasource::file_format::FileFormat;
 use datafusion::datasource::listing::{
     ListingOptions, ListingTable, ListingTableConfig, ListingTableUrl,
 };
-use datafusion::datasource::TableProvider;
+use datafusion::datasource::{MemTable, TableProvider};
 use datafusion::execution::context::SessionConfig;
 use datafusion::prelude::*;
-use deltalake::{DeltaTable, DeltaTableBuilder};
+use deltalake::{DeltaTable, DeltaTableBuilder, DeltaVersion};
+use futures::{StreamExt, TryStreamExt};
 use log::{debug, info};
 use object_store::aws::AmazonS3Builder;
-use std::sync::Arc;
+use object_store::ObjectMeta;
+use std::io::Cursor;
+use std::sync::{Arc, OnceLock};
 use url::Url;
 
-use crate::cli::Format;
+use crate::error::{AdtError, Result};
 use crate::utils::ensure_scheme;
 
-pub struct TableContext {
-    ctx: SessionContext,
+/// Name `name` is registered under: unquoted identifiers are lowercased
+fn registered_name(name: &str) -> String {
+    TableReference::from(name).table().to_owned()
+}
+
+/// `name` quoted as a sql identifier, keeping its case
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Query of every row of the table registered as `name`
+pub fn select_all(name: &str) -> String {
+    format!("select * from {}", quote_identifier(&registered_name(name)))
+}
+
+/// Storage format of a table
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Format {
+    Parquet,
+    Delta,
+    Csv,
+    /// newline delimited json
+    Json,
+    /// arrow ipc file (feather v2) or stream
+    Arrow,
+    Avro,
+}
+
+impl Format {
+    /// Name of the format as given on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Parquet => "parquet",
+            Format::Delta => "delta",
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Arrow => "arrow",
+            Format::Avro => "avro",
+        }
+    }
+}
+
+/// Options of the csv and json readers
+#[derive(Clone)]
+pub struct ReadOptions {
+    /// csv field delimiter, tab for .tsv files and comma otherwise when None
+    pub delimiter: Option<u8>,
+    /// csv files have no header line
+    pub no_header: bool,
+    pub quote: u8,
+    pub escape: Option<u8>,
+    /// number of records read to infer the csv or json schema
+    pub infer_sample_size: usize,
+    /// csv or json file schema, as col:type,... (skips inference)
+    pub schema: Option<String>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: None,
+            no_header: false,
+            quote: b'"',
+            escape: None,
+            infer_sample_size: 1000,
+            schema: None,
+        }
+    }
+}
+
+/// A table to register in the session under `name`
+pub struct TableSpec {
+    name: String,
     path: Url,
     partition_spec: Option<Vec<(String, DataType)>>,
-    fmt: Format,
+    /// given format, or the detected one once the table is registered
+    fmt: OnceLock<Format>,
+    version: DeltaVersion,
+    read_options: ReadOptions,
 }
 
-impl TableContext {
-    pub fn new(table_path: &str, partitions: &Option<String>, fmt: Format) -> Self {
+impl TableSpec {
+    /// Table read from a local path or an url (s3://bucket/prefix), the
+    /// newest version of it for delta tables
+    pub fn new(name: &str, table_path: &str) -> Result<Self> {
+        Ok(Self {
+            name: name.to_string(),
+            path: ensure_scheme(table_path)?,
+            partition_spec: None,
+            fmt: OnceLock::new(),
+            version: DeltaVersion::Newest,
+            read_options: ReadOptions::default(),
+        })
+    }
+
+    /// Format of the table, detected at registration when not given
+    pub fn with_format(self, fmt: Format) -> Self {
         Self {
-            ctx: SessionContext::new_with_config(
-                SessionConfig::default().with_information_schema(true),
-            ),
-            path: ensure_scheme(table_path).unwrap(),
-            partition_spec: get_partitions_spec(partitions),
-            fmt: fmt,
+            fmt: OnceLock::from(fmt),
+            ..self
         }
     }
 
+    /// Hive partition columns given as col:type,...
+    pub fn with_partitions(mut self, partitions: &str) -> Result<Self> {
+        self.partition_spec = Some(get_columns_spec(partitions)?);
+        Ok(self)
+    }
+
+    /// Version of a delta table to load
+    pub fn with_version(mut self, version: DeltaVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Reader options of csv and json tables
+    pub fn with_read_options(mut self, read_options: ReadOptions) -> Self {
+        self.read_options = read_options;
+        self
+    }
+
+    /// Name the table is registered and listed in information_schema under
+    pub fn registered_name(&self) -> String {
+        registered_name(&self.name)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &Url {
+        &self.path
+    }
+
+    pub fn format(&self) -> Option<Format> {
+        self.fmt.get().copied()
+    }
+}
+
+/// Statements allowed on a shared session: queries only, DDL, DML and
+/// statements such as `SET` would change the tables or the settings of every
+/// client
+pub fn read_only_options() -> SQLOptions {
+    SQLOptions::new()
+        .with_allow_ddl(false)
+        .with_allow_dml(false)
+        .with_allow_statements(false)
+}
+
+/// A registered table as listed by the catalog browser
+pub struct TableInfo {
+    pub name: String,
+    pub path: Url,
+    pub format: Option<Format>,
+    pub schema: SchemaRef,
+    /// hive partition columns, or the ones of delta tables
+    pub partitions: Vec<String>,
+    /// version of delta tables
+    pub version: Option<i64>,
+    /// properties of delta tables
+    pub properties: Vec<(String, String)>,
+}
+
+/// Session querying a fixed set of tables
+pub struct TableContext {
+    ctx: SessionContext,
+    tables: Vec<TableSpec>,
+}
+
+impl TableContext {
+    /// Session over `tables`, queryable once they are registered
+    pub fn new(tables: Vec<TableSpec>) -> Self {
+        Self::with_context(
+            SessionContext::new_with_config(SessionConfig::default().with_information_schema(true)),
+            tables,
+        )
+    }
+
+    /// Session over `tables` registered in an existing `ctx`
+    pub fn with_context(ctx: SessionContext, tables: Vec<TableSpec>) -> Self {
+        Self { ctx, tables }
+    }
+
     pub fn context(&self) -> &SessionContext {
         &self.ctx
     }
 
-    pub async fn register_table(&self) -> Result<()> {
-        debug!("register table");
-        let provider: Arc<dyn TableProvider> = match self.fmt {
+    pub fn tables(&self) -> &[TableSpec] {
+        &self.tables
+    }
+
+    /// Query of every row of the first table, run when no query is given
+    pub fn default_query(&self) -> String {
+        select_all(self.tables.first().map_or("tbl", |t| t.name()))
+    }
+
+    /// Register every table in the session, loading the delta logs and
+    /// inferring the schema of the other formats
+    pub async fn register_tables(&self) -> Result<()> {
+        for spec in self.tables.iter() {
+            self.register_table(spec).await?;
+        }
+        Ok(())
+    }
+
+    async fn register_table(&self, spec: &TableSpec) -> Result<()> {
+        debug!("register table {}", spec.name);
+        if let Ok(local_path) = spec.path.to_file_path() {
+            if !local_path.exists() {
+                return Err(AdtError::InvalidPath {
+                    path: local_path.display().to_string(),
+                    reason: "no such file or directory".to_string(),
+                });
+            }
+        }
+        // layout of the data files when they match the table format, a single
+        // file is not listed when the format is given
+        let (fmt, kind) = match spec.format() {
+            Some(fmt) => {
+                let kind = spec
+                    .path
+                    .path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .and_then(FileKind::from_file_name)
+                    .filter(|k| k.format == fmt);
+                let kind = match kind {
+                    Some(kind) => Some(kind),
+                    None => self.file_kind(spec, Some(fmt)).await?,
+                };
+                (fmt, kind)
+            }
+            None => {
+                let (fmt, kind) = self.detect_format(spec).await?;
+                info!("{} detected format: {}", spec.name, fmt.name());
+                let _ = spec.fmt.set(fmt);
+                (fmt, kind)
+            }
+        };
+        let kind = kind.filter(|k| k.format == fmt);
+        if fmt != Format::Delta && spec.version != DeltaVersion::Newest {
+            return Err(AdtError::Table(
+                "Time travel (--version/--as-of) is only supported for delta tables".to_string(),
+            ));
+        }
+        let provider: Arc<dyn TableProvider> = match fmt {
             Format::Parquet => {
-                let parquet_table = self.parquet_table_provider().await?;
+                let parquet_table = self.parquet_table_provider(spec, kind).await?;
                 Arc::new(parquet_table)
             }
+            Format::Csv | Format::Json => {
+                let text_table = self.text_table_provider(spec, fmt, kind).await?;
+                Arc::new(text_table)
+            }
+            Format::Arrow => self.arrow_table_provider(spec, kind).await?,
+            Format::Avro => {
+                debug!("get avro table provider");
+                let extension = listing_extension(spec, kind, ".avro");
+                let avro_table = self
+                    .listing_table_provider(spec, Arc::new(AvroFormat), &extension, None)
+                    .await?;
+                Arc::new(avro_table)
+            }
             Format::Delta => {
-                let delta_table = self.delta_table_provider().await?;
+                let delta_table = self.delta_table_provider(spec).await?;
                 Arc::new(delta_table)
             }
         };
-        self.ctx.register_table("tbl", provider)?;
+        if self
+            .ctx
+            .register_table(spec.name.as_str(), provider)?
+            .is_some()
+        {
+            return Err(AdtError::Table(format!(
+                "Table {} is registered more than once",
+                spec.name
+            )));
+        }
         Ok(())
     }
 
+    /// Columns of every table, as listed by information_schema
     pub async fn schema(&self) -> Result<DataFrame> {
-        let schema_query = "show columns from tbl";
+        let table_names: Vec<&str> = self.tables.iter().map(|t| t.name()).collect();
+        self.columns_of(&table_names).await
+    }
+
+    /// Schema, partition columns and delta metadata of every registered table
+    pub async fn tables_info(&self) -> Result<Vec<TableInfo>> {
+        let mut infos = Vec::with_capacity(self.tables.len());
+        for spec in self.tables.iter() {
+            let provider = self.ctx.table_provider(spec.name.as_str()).await?;
+            let mut info = TableInfo {
+                name: spec.name.clone(),
+                path: spec.path.clone(),
+                format: spec.format(),
+                schema: provider.schema(),
+                partitions: spec
+                    .partition_spec
+                    .iter()
+                    .flatten()
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+                version: None,
+                properties: Vec::new(),
+            };
+            if let Some(table) = provider.as_any().downcast_ref::<DeltaTable>() {
+                let metadata = table.metadata()?;
+                info.partitions = metadata.partition_columns.clone();
+                info.version = Some(table.version());
+                info.properties = metadata
+                    .configuration
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone().unwrap_or_default()))
+                    .collect();
+                info.properties.sort();
+            }
+            infos.push(info);
+        }
+        Ok(infos)
+    }
+
+    /// Row count and column statistics of the `name` table as recorded in
+    /// its parquet footers or delta log, without scanning it
+    pub async fn file_statistics(&self, name: &str) -> Result<(SchemaRef, Statistics)> {
+        let spec = self.tables.iter().find(|spec| spec.name == name);
+        let format = spec.and_then(|spec| spec.format());
+        if !matches!(format, Some(Format::Parquet | Format::Delta)) {
+            return Err(AdtError::Table(format!(
+                "Table {} has no file statistics, only parquet and delta tables have some",
+                name
+            )));
+        }
+        let provider = self.ctx.table_provider(name).await?;
+        let statistics = match provider.statistics() {
+            Some(statistics) => statistics,
+            // listing tables gather the statistics of their files when planned
+            None => provider
+                .scan(&self.ctx.state(), None, &[], None)
+                .await?
+                .statistics()?,
+        };
+        Ok((provider.schema(), statistics))
+    }
+
+    /// Columns of the `name` table
+    pub async fn table_schema(&self, name: &str) -> Result<DataFrame> {
+        self.columns_of(&[name]).await
+    }
+
+    async fn columns_of(&self, table_names: &[&str]) -> Result<DataFrame> {
+        let table_names = table_names
+            .iter()
+            .map(|name| format!("'{}'", registered_name(name).replace('\'', "''")))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let schema_query = format!(
+            "select table_catalog, table_schema, table_name, column_name, data_type, is_nullable \
+            from information_schema.columns where table_name in ({}) \
+            order by table_name, ordinal_position",
+            table_names
+        );
         info!("schema query: {}", schema_query);
-        Ok(self.ctx.sql(schema_query).await?)
+        Ok(self.ctx.sql(schema_query.as_str()).await?)
     }
 
-    pub fn build_query(&self, query: String, limit: usize) -> String {
-        let full_query = if query.starts_with("SELECT") || query.starts_with("select") {
-            format!("{} LIMIT {}", query, limit)
-        } else {
-            query.clone()
+    /// `query` with `limit` appended to select statements
+    pub fn build_query(&self, query: String, limit: Option<usize>) -> String {
+        let is_select = query.starts_with("SELECT") || query.starts_with("select");
+        let full_query = match limit {
+            Some(limit) if is_select => format!("{} LIMIT {}", query, limit),
+            _ => query.clone(),
         };
         info!("full query: {}", full_query);
         full_query
     }
 
-    pub async fn exec_query(&self, query: String, limit: usize) -> Result<DataFrame> {
+    /// Plan `query` limited to `limit` rows, the DataFrame runs it once
+    /// collected or streamed
+    pub async fn exec_query(&self, query: String, limit: Option<usize>) -> Result<DataFrame> {
         let full_query = self.build_query(query, limit);
         Ok(self.ctx.sql(full_query.as_str()).await?)
     }
 
-    async fn parquet_table_provider(&self) -> Result<ListingTable> {
-        debug!("register store");
-        let url = &(self.path);
-        match self.path.scheme() {
+    /// Plan `query` for the clients of the servers sharing this session,
+    /// anything else than a query is rejected by `read_only_options`
+    pub async fn read_only_query(&self, query: &str) -> Result<DataFrame> {
+        Ok(self
+            .ctx
+            .sql_with_options(query, read_only_options())
+            .await?)
+    }
+
+    /// Register the object store serving `url` (S3 buckets) in the session
+    pub fn register_object_store(&self, url: &Url) -> Result<()> {
+        match url.scheme() {
             "s3" | "s3a" => {
+                debug!("register store");
+                let bucket = url.host_str().ok_or_else(|| AdtError::InvalidPath {
+                    path: url.to_string(),
+                    reason: "no bucket name".to_string(),
+                })?;
                 let s3 = AmazonS3Builder::from_env()
-                    .with_bucket_name(
-                        url.host_str()
-                            .expect("failed to extract host/bucket from path"),
-                    )
-                    .build()
-                    .expect("Unable to create S3 object store");
+                    .with_bucket_name(bucket)
+                    .build()?;
                 let s3_url =
                     Url::parse(&url[url::Position::BeforeScheme..url::Position::AfterHost])
-                        .expect("Unable to get bucket based S3 url");
+                        .map_err(|e| AdtError::InvalidPath {
+                            path: url.to_string(),
+                            reason: e.to_string(),
+                        })?;
                 let _ = self
                     .ctx
                     .runtime_env()
@@ -99,50 +448,669 @@ impl TableContext {
             }
             _ => (),
         }
+        Ok(())
+    }
+
+    /// A `_delta_log` directory means delta, otherwise the format is given by
+    /// the extension of the data files, returned along with their layout
+    async fn detect_format(&self, spec: &TableSpec) -> Result<(Format, Option<FileKind>)> {
+        self.register_object_store(&spec.path)?;
+        let path = ListingTableUrl::parse(spec.path.as_str())?;
+        let store = self.ctx.runtime_env().object_store(&path)?;
+        let delta_log = path.prefix().child("_delta_log");
+        if let Some(Ok(_)) = store.list(Some(&delta_log)).next().await {
+            return Ok((Format::Delta, None));
+        }
+        match self.file_kind(spec, None).await? {
+            Some(kind) => Ok((kind.format, Some(kind))),
+            None => Err(AdtError::Table(format!(
+                "Unable to detect the format of {}: no delta log nor known file extension, \
+                use --format",
+                spec.path
+            ))),
+        }
+    }
+
+    /// Layout of the first data file with a known extension, of the `format`
+    /// one when given
+    async fn file_kind(
+        &self,
+        spec: &TableSpec,
+        format: Option<Format>,
+    ) -> Result<Option<FileKind>> {
+        self.register_object_store(&spec.path)?;
+        let path = ListingTableUrl::parse(spec.path.as_str())?;
+        let state = self.ctx.state();
+        let store = state.runtime_env().object_store(&path)?;
+        let mut files = path.list_all_files(&state, store.as_ref(), "").await?;
+        while let Some(file) = files.try_next().await? {
+            let kind = file
+                .location
+                .filename()
+                .and_then(FileKind::from_file_name)
+                .filter(|k| format.is_none_or(|format| k.format == format));
+            if let Some(kind) = kind {
+                debug!("{} file layout from {}", spec.name, file.location);
+                return Ok(Some(kind));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn parquet_table_provider(
+        &self,
+        spec: &TableSpec,
+        kind: Option<FileKind>,
+    ) -> Result<ListingTable> {
         debug!("get parquet table provider");
         let file_format = ParquetFormat::default()
             .with_enable_pruning(true)
             .with_skip_metadata(true);
+        let extension = listing_extension(spec, kind, ".parquet");
+        self.listing_table_provider(spec, Arc::new(file_format), &extension, None)
+            .await
+    }
 
+    async fn text_table_provider(
+        &self,
+        spec: &TableSpec,
+        fmt: Format,
+        kind: Option<FileKind>,
+    ) -> Result<ListingTable> {
+        let options = &spec.read_options;
+        let compression = kind
+            .as_ref()
+            .map_or(FileCompressionType::UNCOMPRESSED, |k| k.compression);
+        let default_extension = match fmt {
+            Format::Csv => ".csv",
+            _ => ".json",
+        };
+        let extension = listing_extension(spec, kind, default_extension);
+        let file_format: Arc<dyn FileFormat> = match fmt {
+            Format::Csv => {
+                debug!("get csv table provider");
+                let delimiter = match options.delimiter {
+                    Some(delimiter) => delimiter,
+                    None if extension.starts_with(".tsv") => b'\t',
+                    None => b',',
+                };
+                let csv_format = CsvFormat::default()
+                    .with_delimiter(delimiter)
+                    .with_has_header(!options.no_header)
+                    .with_quote(options.quote)
+                    .with_escape(options.escape)
+                    .with_schema_infer_max_rec(options.infer_sample_size)
+                    .with_file_compression_type(compression);
+                Arc::new(csv_format)
+            }
+            _ => {
+                debug!("get json table provider");
+                let json_format = JsonFormat::default()
+                    .with_schema_infer_max_rec(options.infer_sample_size)
+                    .with_file_compression_type(compression);
+                Arc::new(json_format)
+            }
+        };
+        let schema = options.schema.as_deref().map(|schema| {
+            let fields: Vec<Field> = get_columns_spec(schema)?
+                .into_iter()
+                .map(|(name, data_type)| Field::new(name, data_type, true))
+                .collect();
+            Ok::<_, AdtError>(Arc::new(Schema::new(fields)))
+        });
+        let schema = schema.transpose()?;
+        self.listing_table_provider(spec, file_format, &extension, schema)
+            .await
+    }
+
+    /// Arrow IPC files are read through a listing table, IPC streams have no
+    /// footer to locate record batches from so they are loaded in memory
+    async fn arrow_table_provider(
+        &self,
+        spec: &TableSpec,
+        kind: Option<FileKind>,
+    ) -> Result<Arc<dyn TableProvider>> {
+        self.register_object_store(&spec.path)?;
+        let extension = listing_extension(spec, kind, ".arrow");
+        let path = ListingTableUrl::parse(spec.path.as_str())?;
+        let state = self.ctx.state();
+        let store = state.runtime_env().object_store(&path)?;
+        let files: Vec<ObjectMeta> = path
+            .list_all_files(&state, store.as_ref(), &extension)
+            .await?
+            .try_collect()
+            .await?;
+        let Some(first) = files.first() else {
+            return Err(AdtError::Table(format!(
+                "No {} file found in {}",
+                extension, spec.path
+            )));
+        };
+        if store.get_range(&first.location, 0..6).await?.as_ref() == b"ARROW1" {
+            debug!("get arrow ipc file table provider");
+            // datafusion fails to infer the schema of streamed (remote) files,
+            // so it is read from the footer of the first one
+            let invalid = || AdtError::Table(format!("Invalid arrow ipc file {}", first.location));
+            // the file ends with the footer, its i32 length and the magic
+            let footer_end = first.size.checked_sub(10).ok_or_else(invalid)?;
+            let tail = store
+                .get_range(&first.location, footer_end..first.size)
+                .await?;
+            let footer_len = i32::from_le_bytes([tail[0], tail[1], tail[2], tail[3]]);
+            let footer_start = usize::try_from(footer_len)
+                .ok()
+                .and_then(|footer_len| footer_end.checked_sub(footer_len))
+                .ok_or_else(invalid)?;
+            let footer = store
+                .get_range(&first.location, footer_start..footer_end)
+                .await?;
+            let schema = root_as_footer(&footer)
+                .ok()
+                .and_then(|footer| footer.schema())
+                .map(fb_to_schema)
+                .ok_or_else(invalid)?;
+            let arrow_table = self
+                .listing_table_provider(
+                    spec,
+                    Arc::new(ArrowFormat),
+                    &extension,
+                    Some(Arc::new(schema)),
+                )
+                .await?;
+            return Ok(Arc::new(arrow_table));
+        }
+        debug!("get arrow ipc stream table provider");
+        if spec.partition_spec.is_some() {
+            return Err(AdtError::Table(
+                "Partitions are not supported on arrow ipc streams".to_string(),
+            ));
+        }
+        let mut schema = None;
+        let mut partitions = Vec::with_capacity(files.len());
+        for file in files.iter() {
+            let bytes = store.get(&file.location).await?.bytes().await?;
+            let reader = StreamReader::try_new(Cursor::new(bytes), None).map_err(|e| {
+                AdtError::Table(format!(
+                    "Unable to read arrow ipc stream {}: {}",
+                    file.location, e
+                ))
+            })?;
+            schema.get_or_insert(reader.schema());
+            partitions.push(reader.collect::<Result<Vec<_>, _>>()?);
+        }
+        let table = MemTable::try_new(
+            schema.unwrap_or_else(|| Arc::new(Schema::empty())),
+            partitions,
+        )?;
+        Ok(Arc::new(table))
+    }
+
+    /// Listing table over the files of `spec`, the file schema is inferred
+    /// unless given
+    async fn listing_table_provider(
+        &self,
+        spec: &TableSpec,
+        file_format: Arc<dyn FileFormat>,
+        extension: &str,
+        schema: Option<Arc<Schema>>,
+    ) -> Result<ListingTable> {
+        self.register_object_store(&spec.path)?;
         let listing_common_options =
-            ListingOptions::new(Arc::new(file_format)).with_file_extension(".parquet");
+            ListingOptions::new(file_format).with_file_extension(extension);
 
-        let listing_options = match self.partition_spec.clone() {
+        let listing_options = match spec.partition_spec.clone() {
             Some(parts) => listing_common_options.with_table_partition_cols(parts),
             None => listing_common_options,
         };
 
-        let path = ListingTableUrl::parse(self.path.as_str())?;
-        let table_config = ListingTableConfig::new(path)
-            .with_listing_options(listing_options)
-            .infer_schema(&self.ctx.state())
-            .await?;
+        let path = ListingTableUrl::parse(spec.path.as_str())?;
+        let table_config = ListingTableConfig::new(path).with_listing_options(listing_options);
+        let table_config = match schema {
+            Some(schema) => table_config.with_schema(schema),
+            None => table_config.infer_schema(&self.ctx.state()).await?,
+        };
         let table = ListingTable::try_new(table_config)?;
         Ok(table)
     }
 
-    async fn delta_table_provider(&self) -> Result<DeltaTable> {
+    async fn delta_table_provider(&self, spec: &TableSpec) -> Result<DeltaTable> {
         debug!("get delta table provider");
         deltalake::aws::register_handlers(None);
-        Ok(DeltaTableBuilder::from_uri(self.path.as_str())
-            .without_tombstones()
-            .load()
-            .await?)
+        let builder = DeltaTableBuilder::from_uri(spec.path.as_str()).without_tombstones();
+        let table = match spec.version {
+            DeltaVersion::Newest => builder.load().await?,
+            DeltaVersion::Version(version) => {
+                builder.with_version(version).load().await.map_err(|e| {
+                    AdtError::DeltaLog(format!(
+                        "Unable to load version {} of delta table {}: the version does not exist \
+                        or its log has been cleaned up: {}",
+                        version, spec.path, e
+                    ))
+                })?
+            }
+            DeltaVersion::Timestamp(ts) => {
+                let table = builder.with_timestamp(ts).load().await.map_err(|e| {
+                    AdtError::DeltaLog(format!(
+                        "Unable to load delta table {} as of {}: {}",
+                        spec.path, ts, e
+                    ))
+                })?;
+                // deltalake falls back to the oldest available version when the
+                // timestamp predates it, which would silently show the wrong data
+                let commit_ts = table.snapshot()?.version_timestamp(table.version());
+                if let Some(commit_ts) = commit_ts.filter(|c| *c > ts.timestamp_millis()) {
+                    return Err(AdtError::DeltaLog(format!(
+                        "Delta table {} has no version as of {}: its oldest available version {} \
+                        was committed at {}",
+                        spec.path,
+                        ts,
+                        table.version(),
+                        DateTime::from_timestamp_millis(commit_ts).unwrap_or_default()
+                    )));
+                }
+                table
+            }
+        };
+        info!("{} delta table version: {}", spec.name, table.version());
+        Ok(table)
+    }
+}
+
+/// Format, compression and extension of a data file
+struct FileKind {
+    format: Format,
+    compression: FileCompressionType,
+    extension: String,
+}
+
+impl FileKind {
+    fn from_file_name(name: &str) -> Option<Self> {
+        if name.starts_with(['_', '.']) {
+            return None;
+        }
+        let (stem, ext) = name.rsplit_once('.')?;
+        let compression = match ext {
+            "gz" => FileCompressionType::GZIP,
+            "bz2" => FileCompressionType::BZIP2,
+            "xz" => FileCompressionType::XZ,
+            "zst" => FileCompressionType::ZSTD,
+            _ => FileCompressionType::UNCOMPRESSED,
+        };
+        let (stem, ext) = if compression.is_compressed() {
+            stem.rsplit_once('.')?
+        } else {
+            (stem, ext)
+        };
+        let format = match ext {
+            "csv" | "tsv" => Format::Csv,
+            "json" | "ndjson" | "jsonl" => Format::Json,
+            "parquet" | "parq" => Format::Parquet,
+            "arrow" | "arrows" | "feather" | "ipc" => Format::Arrow,
+            "avro" => Format::Avro,
+            _ => return None,
+        };
+        // only the text formats are read through a compression codec
+        if compression.is_compressed() && !matches!(format, Format::Csv | Format::Json) {
+            return None;
+        }
+        Some(Self {
+            format,
+            compression,
+            extension: name[stem.len()..].to_string(),
+        })
     }
 }
 
-fn get_partitions_spec(partitions: &Option<String>) -> Option<Vec<(String, DataType)>> {
-    if let Some(parts) = partitions.as_deref() {
-        let mut vec = Vec::new();
-        parts
-            .split(',')
-            .map(|s| s.trim())
-            .map(|s| s.split(':').collect())
-            .for_each(|t: Vec<&str>| {
-                vec.push((t[0].to_string(), crate::utils::type_from_str(t[1]).unwrap()))
-            });
-        Some(vec)
-    } else {
-        None
+/// Extension of the listed files: the one of the data files found, else the
+/// one of a single file whatever it is (.txt...), else the format default
+fn listing_extension(spec: &TableSpec, kind: Option<FileKind>, default: &str) -> String {
+    if let Some(kind) = kind {
+        return kind.extension;
+    }
+    match spec.path.path().rsplit_once('/') {
+        Some((_, file)) => file
+            .rsplit_once('.')
+            .map_or(default.to_string(), |(_, ext)| format!(".{}", ext)),
+        None => default.to_string(),
+    }
+}
+
+/// Columns given as col:type,...
+fn get_columns_spec(columns: &str) -> Result<Vec<(String, DataType)>> {
+    let malformed = |reason: String| AdtError::MalformedSpec {
+        spec: columns.to_string(),
+        reason,
+    };
+    columns
+        .split(',')
+        .map(|s| s.trim())
+        .map(|column| match column.split_once(':') {
+            Some((name, _)) if name.trim().is_empty() => {
+                Err(malformed(format!("no name in '{}'", column)))
+            }
+            Some((name, type_str)) => Ok((
+                name.trim().to_string(),
+                crate::utils::type_from_str(type_str.trim())?,
+            )),
+            None => Err(malformed(format!("no type in '{}'", column))),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::Int64Array;
+    use datafusion::arrow::datatypes::Field;
+    use datafusion::arrow::ipc::writer::{FileWriter, StreamWriter};
+    use datafusion::arrow::record_batch::RecordBatch;
+    use datafusion::arrow::util::pretty::pretty_format_batches;
+
+    #[test]
+    fn columns_spec() {
+        let columns = get_columns_spec("year:int, month : int,day:string").unwrap();
+        let names: Vec<&str> = columns.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["year", "month", "day"]);
+        assert_eq!(columns[0].1, DataType::Int32);
+        assert_eq!(columns[2].1, DataType::Utf8);
+    }
+
+    #[test]
+    fn malformed_columns_spec() {
+        for spec in ["year", "year:int,month", ":int", "year:nope"] {
+            assert!(get_columns_spec(spec).is_err(), "{}", spec);
+        }
+        assert!(matches!(
+            get_columns_spec("year"),
+            Err(AdtError::MalformedSpec { .. })
+        ));
+    }
+
+    fn spec(path: &str, partitions: Option<&str>, format: Format) -> TableSpec {
+        let spec = TableSpec::new("tbl", path).unwrap().with_format(format);
+        match partitions {
+            Some(partitions) => spec.with_partitions(partitions).unwrap(),
+            None => spec,
+        }
+    }
+
+    async fn query(spec: TableSpec, sql: &str) -> String {
+        let tblctx = TableContext::new(vec![spec]);
+        tblctx.register_tables().await.unwrap();
+        let batches = tblctx
+            .context()
+            .sql(sql)
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        pretty_format_batches(&batches).unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn csv_read_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("rows.csv");
+        std::fs::write(&file, "1;'a;b'\n2;c\n").unwrap();
+        let spec = spec(file.to_str().unwrap(), None, Format::Csv).with_read_options(ReadOptions {
+            delimiter: Some(b';'),
+            no_header: true,
+            quote: b'\'',
+            schema: Some("id:bigint,name:string".to_string()),
+            ..ReadOptions::default()
+        });
+        let expected = [
+            "+----+------+",
+            "| id | name |",
+            "+----+------+",
+            "| 1  | a;b  |",
+            "| 2  | c    |",
+            "+----+------+",
+        ];
+        assert_eq!(
+            query(spec, "select * from tbl order by id").await,
+            expected.join("\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn json_schema_override() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("rows.json");
+        std::fs::write(&file, "{\"id\": 1, \"name\": \"a\"}\n{\"id\": 2}\n").unwrap();
+        let path = file.to_str().unwrap();
+        let expected = [
+            "+-------+",
+            "| count |",
+            "+-------+",
+            "| 2     |",
+            "+-------+",
+        ];
+        assert_eq!(
+            query(
+                spec(path, None, Format::Json),
+                "select count(*) as count from tbl"
+            )
+            .await,
+            expected.join("\n")
+        );
+        let spec = spec(path, None, Format::Json).with_read_options(ReadOptions {
+            schema: Some("id:int".to_string()),
+            ..ReadOptions::default()
+        });
+        let expected = ["+----+", "| id |", "+----+", "| 1  |", "| 2  |", "+----+"];
+        assert_eq!(
+            query(spec, "select * from tbl order by id").await,
+            expected.join("\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn csv_hive_partitions() {
+        let dir = tempfile::tempdir().unwrap();
+        for (year, content) in [("2023", "id\n1\n2\n"), ("2024", "id\n3\n")] {
+            let partition = dir.path().join(format!("year={}", year));
+            std::fs::create_dir(&partition).unwrap();
+            std::fs::write(partition.join("part-0.csv"), content).unwrap();
+        }
+        let spec = spec(dir.path().to_str().unwrap(), Some("year:int"), Format::Csv);
+        let expected = [
+            "+------+-------+",
+            "| year | count |",
+            "+------+-------+",
+            "| 2023 | 2     |",
+            "| 2024 | 1     |",
+            "+------+-------+",
+        ];
+        assert_eq!(
+            query(
+                spec,
+                "select year, count(*) as count from tbl group by year order by year"
+            )
+            .await,
+            expected.join("\n")
+        );
+    }
+
+    async fn count_rows(spec: TableSpec) -> usize {
+        let tblctx = TableContext::new(vec![spec]);
+        tblctx.register_tables().await.unwrap();
+        tblctx
+            .context()
+            .sql("select * from tbl")
+            .await
+            .unwrap()
+            .count()
+            .await
+            .unwrap()
+    }
+
+    fn id_batch(ids: Vec<i64>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(ids))]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn arrow_ipc_files_and_streams() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = std::fs::File::create(dir.path().join("a.arrow")).unwrap();
+        let batch = id_batch(vec![1, 2]);
+        let mut writer = FileWriter::try_new(file, &batch.schema()).unwrap();
+        writer.write(&batch).unwrap();
+        writer.write(&id_batch(vec![3])).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            count_rows(spec(dir.path().to_str().unwrap(), None, Format::Arrow)).await,
+            3
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        for (name, ids) in [("a.arrows", vec![1]), ("b.arrows", vec![2, 3, 4])] {
+            let file = std::fs::File::create(dir.path().join(name)).unwrap();
+            let batch = id_batch(ids);
+            let mut writer = StreamWriter::try_new(file, &batch.schema()).unwrap();
+            writer.write(&batch).unwrap();
+            writer.finish().unwrap();
+        }
+        assert_eq!(
+            count_rows(spec(dir.path().to_str().unwrap(), None, Format::Arrow)).await,
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn arrow_ipc_file_without_footer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.arrow");
+        let mut bytes = b"ARROW1\0\0".to_vec();
+        bytes.extend_from_slice(&[0xff; 4]);
+        bytes.extend_from_slice(b"ARROW1");
+        std::fs::write(&path, bytes).unwrap();
+        let tblctx = TableContext::new(vec![spec(path.to_str().unwrap(), None, Format::Arrow)]);
+        assert!(matches!(
+            tblctx.register_tables().await,
+            Err(AdtError::Table(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn avro_files() {
+        use apache_avro::types::Record;
+        let dir = tempfile::tempdir().unwrap();
+        let schema = apache_avro::Schema::parse_str(
+            r#"{"type": "record", "name": "row", "fields": [
+                {"name": "id", "type": "long"}, {"name": "name", "type": "string"}
+            ]}"#,
+        )
+        .unwrap();
+        let file = std::fs::File::create(dir.path().join("rows.avro")).unwrap();
+        let mut writer = apache_avro::Writer::new(&schema, file);
+        for (id, name) in [(1, "a"), (2, "b")] {
+            let mut record = Record::new(writer.schema()).unwrap();
+            record.put("id", id as i64);
+            record.put("name", name);
+            writer.append(record).unwrap();
+        }
+        writer.flush().unwrap();
+        let spec = spec(dir.path().to_str().unwrap(), None, Format::Avro);
+        let expected = [
+            "+----+------+",
+            "| id | name |",
+            "+----+------+",
+            "| 1  | a    |",
+            "| 2  | b    |",
+            "+----+------+",
+        ];
+        assert_eq!(
+            query(spec, "select * from tbl order by id").await,
+            expected.join("\n")
+        );
+    }
+
+    #[test]
+    fn file_kind_from_name() {
+        let kind = FileKind::from_file_name("part-0.parquet").unwrap();
+        assert_eq!(kind.format, Format::Parquet);
+        assert!(!kind.compression.is_compressed());
+        assert_eq!(kind.extension, ".parquet");
+        let kind = FileKind::from_file_name("data.tsv").unwrap();
+        assert_eq!(kind.format, Format::Csv);
+        assert_eq!(kind.extension, ".tsv");
+        assert_eq!(
+            FileKind::from_file_name("rows.jsonl").unwrap().format,
+            Format::Json
+        );
+        assert_eq!(
+            FileKind::from_file_name("batch.feather").unwrap().format,
+            Format::Arrow
+        );
+    }
+
+    #[test]
+    fn compressed_file_kind_from_name() {
+        let kind = FileKind::from_file_name("data.tsv.gz").unwrap();
+        assert_eq!(kind.format, Format::Csv);
+        assert_eq!(kind.compression, FileCompressionType::GZIP);
+        assert_eq!(kind.extension, ".tsv.gz");
+        let kind = FileKind::from_file_name("a.b.json.zst").unwrap();
+        assert_eq!(kind.format, Format::Json);
+        assert_eq!(kind.compression, FileCompressionType::ZSTD);
+        assert_eq!(kind.extension, ".json.zst");
+        // binary formats are not read through a codec
+        assert!(FileKind::from_file_name("part-0.parquet.gz").is_none());
+        assert!(FileKind::from_file_name("data.gz").is_none());
+    }
+
+    #[test]
+    fn file_kind_of_other_files() {
+        for name in [
+            "_SUCCESS",
+            ".part-0.parquet.crc",
+            "_metadata.parquet",
+            "notes.txt",
+            "README",
+        ] {
+            assert!(FileKind::from_file_name(name).is_none(), "{}", name);
+        }
+    }
+
+    fn gzip(path: &std::path::Path, content: &str) {
+        use std::io::Write;
+        let file = std::fs::File::create(path).unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn given_format_lists_compressed_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        gzip(&dir.path().join("a.csv.gz"), "id,name\n1,a\n2,b\n");
+        gzip(&dir.path().join("b.csv.gz"), "id,name\n3,c\n");
+        std::fs::write(dir.path().join("_SUCCESS"), "").unwrap();
+        let path = dir.path().to_str().unwrap();
+        assert_eq!(count_rows(spec(path, None, Format::Csv)).await, 3);
+        let spec = TableSpec::new("tbl", path).unwrap();
+        assert_eq!(count_rows(spec).await, 3);
+    }
+
+    #[tokio::test]
+    async fn given_format_lists_its_own_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.tsv"), "id\tname\n1\ta,b\n").unwrap();
+        std::fs::write(dir.path().join("rows.jsonl"), "{\"id\": 1}\n{\"id\": 2}\n").unwrap();
+        let path = dir.path().to_str().unwrap();
+        assert_eq!(count_rows(spec(path, None, Format::Csv)).await, 1);
+        assert_eq!(count_rows(spec(path, None, Format::Json)).await, 2);
+    }
+
+    #[test]
+    fn quoted_identifiers() {
+        assert_eq!(quote_identifier("Price"), "\"Price\"");
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+        // registered lowercased unless it is not a plain identifier
+        assert_eq!(select_all("MyTable"), "select * from \"mytable\"");
+        assert_eq!(select_all("my table"), "select * from \"my table\"");
     }
 }
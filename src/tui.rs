@@ -1,36 +1,588 @@
 use std::{
-    error::Error,
+    future::Future,
     io,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
+use arrow::datatypes::{Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
+use datafusion::dataframe::DataFrame;
 use ratatui::{prelude::*, widgets::*};
+use tokio::sync::{mpsc, oneshot};
+use unicode_width::UnicodeWidthStr;
+
+use crate::error::{AdtError, Result};
+use crate::export::{self, ExportOptions, OutputFormat, WriteMode};
+use crate::table::{self, TableContext, TableInfo};
+use crate::utils::ensure_scheme;
+
+mod catalog;
+mod clipboard;
+mod editor;
+mod grid;
+mod queries;
+mod search;
+mod tab;
+
+use catalog::Catalog;
+use clipboard::YankFormat;
+use editor::Editor;
+use grid::Selection;
+use queries::Picker;
+use search::Pattern;
+use tab::Tab;
+
+pub use grid::type_name;
+pub(crate) use grid::{truncate, WIDTH_SAMPLE_ROWS};
+
+/// Tabs are switched with the number keys
+const MAX_TABS: usize = 9;
+
+/// Full value of the cell under the cursor
+struct Popup {
+    title: String,
+    text: String,
+    scroll: u16,
+}
+
+/// Line edited in the status bar
+struct Prompt {
+    target: PromptTarget,
+    input: String,
+    regex: bool,
+}
+
+enum PromptTarget {
+    Search,
+    /// filter of a column
+    Filter(usize),
+    /// path the results are saved to
+    Save,
+}
+
+/// Registered tables the queries of the editor are run on
+struct Session {
+    tblctx: Arc<TableContext>,
+    limit: Option<usize>,
+    editor: Editor,
+    editor_shown: bool,
+    /// tables browser, once their info is received
+    catalog: Option<Catalog>,
+    catalog_shown: bool,
+    tables_info: Option<oneshot::Receiver<Result<Vec<TableInfo>, AdtError>>>,
+    /// search of the query history
+    picker: Option<Picker>,
+    focus: Focus,
+    /// error of the last query, shown until the next one
+    error: Option<String>,
+}
+
+/// Pane the keys go to
+#[derive(Clone, Copy, PartialEq)]
+enum Focus {
+    Results,
+    Editor,
+    Catalog,
+}
+
+/// Error shown in the error pane, with its hint
+fn error_text(err: &AdtError) -> String {
+    match err.hint() {
+        Some(hint) => format!("{}\nHint: {}", err, hint),
+        None => err.to_string(),
+    }
+}
+
+/// Query to run, trailing semicolons and comments would break the appended
+/// limit
+fn trim_query(text: &str) -> Option<String> {
+    let query = text.trim().trim_end_matches(';').trim_end();
+    (!query.is_empty()).then(|| format!("{}\n", query))
+}
+
+/// First line of a query, as the title of its tab
+fn query_title(query: &str) -> String {
+    let line = query.lines().find(|line| !line.trim().is_empty());
+    truncate(line.unwrap_or_default().trim(), 24)
+}
+
+/// Path of a save prompt input, followed by an optional output format and
+/// delta write mode, e.g. `s3://bucket/table delta append`
+fn save_target(input: &str) -> Result<(String, ExportOptions), String> {
+    let mut words = input.split_whitespace();
+    let path = words.next().ok_or("No path to save to")?.to_string();
+    let mut options = ExportOptions::default();
+    for word in words {
+        if let Some(format) = OutputFormat::from_name(word) {
+            options.output_format = Some(format);
+        } else if let Some(mode) = WriteMode::from_name(word) {
+            options.mode = mode;
+        } else {
+            return Err(format!("Unknown output format or write mode {}", word));
+        }
+    }
+    Ok((path, options))
+}
 
-#[derive(Default)]
 struct Tui {
-    pub vertical_scroll: u16,
-    pub horizontal_scroll: u16,
+    tabs: Vec<Tab>,
+    current: usize,
+    popup: Option<Popup>,
+    prompt: Option<Prompt>,
+    /// shown in the status bar until the next key
+    message: Option<String>,
+    yank_format: YankFormat,
+    /// exports of the results, sending their outcome
+    saves: Vec<oneshot::Receiver<Result<String>>>,
+    session: Option<Session>,
+}
+
+impl Tui {
+    fn new(tab: Tab) -> Self {
+        Self {
+            tabs: vec![tab],
+            current: 0,
+            popup: None,
+            prompt: None,
+            message: None,
+            yank_format: YankFormat::default(),
+            saves: Vec::new(),
+            session: None,
+        }
+    }
+
+    fn tab(&self) -> &Tab {
+        &self.tabs[self.current]
+    }
+
+    fn tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.current]
+    }
+
+    /// Take the rows received by every tab since the last tick, along with
+    /// the tables of the catalog and the outcome of the exports
+    fn poll(&mut self) {
+        for index in 0..self.tabs.len() {
+            let tab = &mut self.tabs[index];
+            if let Some(err) = tab.poll() {
+                match self.session.as_mut() {
+                    Some(session) => session.error = Some(error_text(&err)),
+                    None => self.message = Some(err.to_string()),
+                }
+            }
+            if self.tabs[index].save.is_some() && !self.tabs[index].is_loading() {
+                self.start_save(index);
+            }
+        }
+        self.saves.retain_mut(|receiver| match receiver.try_recv() {
+            Ok(Ok(saved)) => {
+                self.message = Some(saved);
+                false
+            }
+            Ok(Err(err)) => {
+                self.message = Some(format!("Cannot save: {:#}", err));
+                false
+            }
+            Err(oneshot::error::TryRecvError::Empty) => true,
+            Err(oneshot::error::TryRecvError::Closed) => false,
+        });
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        if let Some(receiver) = session.tables_info.as_mut() {
+            match receiver.try_recv() {
+                Ok(Ok(infos)) => session.catalog = Some(Catalog::new(&infos)),
+                Ok(Err(err)) => session.error = Some(error_text(&err)),
+                Err(oneshot::error::TryRecvError::Empty) => {}
+                Err(oneshot::error::TryRecvError::Closed) => session.tables_info = None,
+            }
+            if session.catalog.is_some() || session.error.is_some() {
+                session.tables_info = None;
+            }
+        }
+    }
+
+    /// Show the results of `plan` in the current tab or in a new one
+    fn open(
+        &mut self,
+        title: &str,
+        new_tab: bool,
+        plan: impl Future<Output = crate::error::Result<DataFrame>> + Send + 'static,
+    ) {
+        if new_tab {
+            if self.tabs.len() >= MAX_TABS {
+                self.message = Some(format!("At most {} tabs, close one with x", MAX_TABS));
+                return;
+            }
+            self.tabs.push(Tab::new(title, Arc::new(Schema::empty())));
+            self.current = self.tabs.len() - 1;
+        }
+        self.tab_mut().run(title, plan);
+        if let Some(session) = self.session.as_mut() {
+            session.error = None;
+        }
+    }
+
+    /// Run `query` in the current tab or in a new one, explained or not
+    fn run(&mut self, query: String, new_tab: bool, explain: bool) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        let (tblctx, limit) = (session.tblctx.clone(), session.limit);
+        let (title, query) = if explain {
+            ("explain".to_string(), format!("explain {}", query))
+        } else {
+            (query_title(&query), query)
+        };
+        self.open(&title, new_tab, async move {
+            tblctx.exec_query(query, limit).await
+        });
+    }
+
+    /// Run a query typed or recalled, it is added to the history
+    fn submit_query(&mut self, text: &str, new_tab: bool) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        let Some(query) = trim_query(text) else {
+            return;
+        };
+        if session.editor.push_history(query.trim_end().to_string()) {
+            queries::append_history(query.trim_end());
+        }
+        self.run(query, new_tab, false);
+    }
+
+    /// Columns of every table in a new tab
+    fn show_schema(&mut self) {
+        let Some(session) = self.session.as_ref() else {
+            return;
+        };
+        let tblctx = session.tblctx.clone();
+        self.open("schema", true, async move { tblctx.schema().await });
+    }
+
+    fn close_tab(&mut self) {
+        if self.tabs.len() > 1 {
+            self.tabs.remove(self.current);
+            self.current = self.current.min(self.tabs.len() - 1);
+        }
+    }
+
+    /// Show a pane and move the focus to it, or hide it
+    fn toggle_pane(&mut self, pane: Focus, focus: bool) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        let shown = match pane {
+            Focus::Editor => &mut session.editor_shown,
+            Focus::Catalog => &mut session.catalog_shown,
+            Focus::Results => return,
+        };
+        *shown = focus || !*shown;
+        session.focus = if focus { pane } else { Focus::Results };
+        if pane == Focus::Catalog && session.catalog.is_none() && session.tables_info.is_none() {
+            let (sender, receiver) = oneshot::channel();
+            let tblctx = session.tblctx.clone();
+            tokio::spawn(async move {
+                let _ = sender.send(tblctx.tables_info().await);
+            });
+            session.tables_info = Some(receiver);
+        }
+    }
+
+    /// Act on a key pressed in the catalog
+    fn catalog_action(&mut self, action: catalog::Action) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        match action {
+            catalog::Action::None => {}
+            catalog::Action::Insert(column) => {
+                session.editor.insert(&table::quote_identifier(&column));
+                session.editor_shown = true;
+                session.focus = Focus::Editor;
+            }
+            catalog::Action::Preview(name) => self.run(table::select_all(&name), false, false),
+        }
+    }
+
+    fn open_picker(&mut self) {
+        if let Some(session) = self.session.as_mut() {
+            session.picker = Some(Picker::new(session.editor.history()));
+        }
+    }
+
+    /// Act on a key pressed in the history picker
+    fn picker_action(&mut self, action: queries::Action) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        match action {
+            queries::Action::None => {}
+            queries::Action::Close => session.picker = None,
+            queries::Action::Recall(query) => {
+                session.picker = None;
+                session.editor.set_text(&query);
+                session.editor_shown = true;
+                session.focus = Focus::Editor;
+            }
+            queries::Action::Run(query, new_tab) => {
+                session.picker = None;
+                session.editor.set_text(&query);
+                self.submit_query(&query, new_tab);
+            }
+        }
+    }
+
+    /// Copy the selected cells to the clipboard, or the cursor cell value
+    fn yank(&mut self) {
+        let format = self.yank_format;
+        let tab = self.tab_mut();
+        let (grid, view) = (&tab.grid, &mut tab.view);
+        let (text, copied) = match view.selected(grid) {
+            Some((rows, cols)) => {
+                let copied = format!("Copied {} rows as {}", rows.len(), format.name());
+                let text = grid
+                    .to_batch(rows, cols)
+                    .and_then(|batch| format.format(&batch));
+                match text {
+                    Ok(text) => (text, copied),
+                    Err(err) => {
+                        self.message = Some(format!("Cannot copy: {}", err));
+                        return;
+                    }
+                }
+            }
+            None if grid.num_rows() > 0 => {
+                let value = grid.value(view.row, view.col).unwrap_or_default();
+                (value, "Copied cell value".to_string())
+            }
+            None => return,
+        };
+        view.selection = None;
+        self.message = Some(match clipboard::copy(&text) {
+            Ok(()) => copied,
+            Err(err) => format!("Cannot copy: {}", err),
+        });
+    }
+
+    /// Save the rows of the current tab once they are all received, to the
+    /// path of `input` optionally followed by a format and a write mode
+    fn save(&mut self, input: &str) {
+        let target = match save_target(input) {
+            Ok(target) => target,
+            Err(err) => {
+                self.message = Some(err);
+                return;
+            }
+        };
+        let tab = self.tab_mut();
+        tab.save = Some(target);
+        tab.fetch_all = true;
+        if tab.is_loading() {
+            self.message = Some("Fetching every row before saving".to_string());
+        }
+    }
+
+    /// Export the shown rows and columns of a tab with the writers of
+    /// `view --output-path`
+    fn start_save(&mut self, index: usize) {
+        let tab = &mut self.tabs[index];
+        let Some((path, options)) = tab.save.take() else {
+            return;
+        };
+        let grid = &tab.grid;
+        let batch = match grid.to_batch(0..grid.num_rows(), 0..grid.num_columns()) {
+            Ok(batch) => batch,
+            Err(err) => {
+                self.message = Some(format!("Cannot save: {}", err));
+                return;
+            }
+        };
+        // s3 outputs need the stores registered in the session
+        let tblctx = match self.session.as_ref() {
+            Some(session) => session.tblctx.clone(),
+            None => Arc::new(TableContext::new(vec![])),
+        };
+        let (sender, receiver) = oneshot::channel();
+        tokio::spawn(async move {
+            let rows = batch.num_rows();
+            let saved = async {
+                let url = ensure_scheme(&path)?;
+                tblctx.register_object_store(&url)?;
+                let df = tblctx.context().read_batch(batch)?;
+                export::export(df, url.as_str(), &options).await?;
+                Ok(format!("Saved {} rows to {}", rows, path))
+            };
+            let _ = sender.send(saved.await);
+        });
+        self.saves.push(receiver);
+    }
+
+    fn open_prompt(&mut self, target: PromptTarget) {
+        if let PromptTarget::Filter(col) = target {
+            if col >= self.tab().grid.num_columns() {
+                return;
+            }
+        }
+        self.prompt = Some(Prompt {
+            target,
+            input: String::new(),
+            regex: false,
+        });
+    }
+
+    /// Search, filter or save with the prompt input, an empty one clears
+    /// the search or filter
+    fn submit(&mut self, prompt: Prompt) {
+        if let PromptTarget::Save = prompt.target {
+            if !prompt.input.is_empty() {
+                self.save(&prompt.input);
+            }
+            return;
+        }
+        let pattern = match prompt.input.as_str() {
+            "" => None,
+            input => match Pattern::new(input, prompt.regex) {
+                Ok(pattern) => Some(pattern),
+                Err(err) => {
+                    let reason = err.to_string();
+                    let reason = reason.lines().last().unwrap_or_default();
+                    let reason = reason.trim_start_matches("error: ");
+                    self.message = Some(format!("Invalid regex: {}", reason));
+                    return;
+                }
+            },
+        };
+        match prompt.target {
+            PromptTarget::Search => {
+                self.tab_mut().set_search(pattern);
+                self.jump(true);
+            }
+            PromptTarget::Filter(col) => self.tab_mut().set_filter(col, pattern),
+            PromptTarget::Save => {}
+        }
+    }
+
+    /// Sort by the cursor column, along with the other sort keys when `multi`
+    fn sort(&mut self, multi: bool) {
+        if let Err(err) = self.tab_mut().sort(multi) {
+            self.message = Some(format!("Cannot sort: {}", err));
+        }
+    }
+
+    /// Move the cursor to the next or previous match of the search
+    fn jump(&mut self, forward: bool) {
+        if !self.tab_mut().jump(forward) {
+            if let Some(search) = &self.tab().search {
+                self.message = Some(format!("Pattern not found: {}", search.pattern().input()));
+            }
+        }
+    }
+
+    fn open_popup(&mut self) {
+        let (grid, view) = (&self.tab().grid, &self.tab().view);
+        if grid.num_rows() == 0 {
+            return;
+        }
+        let field = grid.field(view.col);
+        self.popup = Some(Popup {
+            title: format!(" {}: {} ", field.name(), type_name(field.data_type())),
+            text: grid.detail(view.row, view.col),
+            scroll: 0,
+        });
+    }
+}
+
+/// Show record batches in the grid viewer until `q` is pressed
+pub fn show_in_tui(batches: Vec<RecordBatch>) -> Result<()> {
+    let schema = batches
+        .first()
+        .map_or_else(|| Arc::new(Schema::empty()), |batch| batch.schema());
+    let mut tab = Tab::new("results", schema);
+    for batch in batches {
+        tab.grid.push(batch);
+    }
+    show(Tui::new(tab))
+}
+
+/// Show record batches as they are received, closing the viewer drops the
+/// receiver so the sender can stop
+pub fn show_batches_in_tui(schema: SchemaRef, receiver: mpsc::Receiver<RecordBatch>) -> Result<()> {
+    let mut tab = Tab::new("results", schema);
+    tab.receiver = Some(receiver);
+    show(Tui::new(tab))
+}
+
+/// Show the results of `query` along with an editor running other queries
+/// on the tables of `tblctx`, their results being limited to `limit` rows.
+/// The results are taken as they are scrolled to, unless `fetch_all`.
+pub fn show_query_in_tui(
+    tblctx: Arc<TableContext>,
+    query: &str,
+    limit: Option<usize>,
+    schema: SchemaRef,
+    receiver: mpsc::Receiver<RecordBatch>,
+    fetch_all: bool,
+) -> Result<()> {
+    let mut tab = Tab::new(&query_title(query), schema);
+    tab.receiver = Some(receiver);
+    tab.fetch_all = fetch_all;
+    let mut tui = Tui::new(tab);
+    let mut editor = Editor::new(query).with_history(queries::load_history());
+    editor.push_history(query.to_string());
+    tui.session = Some(Session {
+        tblctx,
+        limit,
+        editor,
+        editor_shown: true,
+        catalog: None,
+        catalog_shown: false,
+        tables_info: None,
+        picker: None,
+        focus: Focus::Results,
+        error: None,
+    });
+    show(tui)
 }
 
-pub fn show_in_tui(text: &str) -> Result<(), Box<dyn Error>> {
+fn show(tui: Tui) -> Result<()> {
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    // tells ctrl+enter apart from enter, to run the query of the editor
+    let enhanced = tui.session.is_some() && supports_keyboard_enhancement().unwrap_or(false);
+    if enhanced {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // create tui and run it
     let tick_rate = Duration::from_millis(250);
-    let tui = Tui::default();
-    let res = run_tui(&mut terminal, tui, tick_rate, text);
+    let res = run_tui(&mut terminal, tui, tick_rate);
 
     // restore terminal
+    if enhanced {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
@@ -39,51 +591,175 @@ pub fn show_in_tui(text: &str) -> Result<(), Box<dyn Error>> {
     )?;
     terminal.show_cursor()?;
 
-    if let Err(err) = res {
-        println!("{err:?}");
-    }
-
-    Ok(())
+    Ok(res?)
 }
 
 fn run_tui<B: Backend>(
     terminal: &mut Terminal<B>,
     mut tui: Tui,
     tick_rate: Duration,
-    text: &str,
 ) -> io::Result<()> {
     let mut last_tick = Instant::now();
     loop {
-        terminal.draw(|f| ui(f, &mut tui, text))?;
+        tui.poll();
+        terminal.draw(|f| ui(f, &mut tui))?;
 
         let timeout = tick_rate.saturating_sub(last_tick.elapsed());
         if crossterm::event::poll(timeout)? {
             if let Event::Key(key) = event::read()? {
-                match (key.code, key.modifiers) {
-                    (KeyCode::Char('q'), KeyModifiers::NONE) => return Ok(()),
-                    (KeyCode::Char('j') | KeyCode::Down, KeyModifiers::NONE) => {
-                        tui.vertical_scroll = tui.vertical_scroll.saturating_add(1);
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+                let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                tui.message = None;
+                if let Some(prompt) = tui.prompt.as_mut() {
+                    match key.code {
+                        KeyCode::Esc => tui.prompt = None,
+                        KeyCode::Enter => {
+                            if let Some(prompt) = tui.prompt.take() {
+                                tui.submit(prompt);
+                            }
+                        }
+                        KeyCode::Char('r')
+                            if ctrl && !matches!(prompt.target, PromptTarget::Save) =>
+                        {
+                            prompt.regex = !prompt.regex
+                        }
+                        KeyCode::Backspace => {
+                            prompt.input.pop();
+                        }
+                        KeyCode::Char(c) => prompt.input.push(c),
+                        _ => {}
+                    }
+                    continue;
+                }
+                if let Some(session) = tui.session.as_mut() {
+                    if let Some(picker) = session.picker.as_mut() {
+                        let action = picker.handle_key(key, session.editor.history());
+                        tui.picker_action(action);
+                        continue;
+                    }
+                    match session.focus {
+                        Focus::Editor => {
+                            let run = matches!(key.code, KeyCode::Enter)
+                                && key
+                                    .modifiers
+                                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+                            let text = session.editor.text();
+                            match key.code {
+                                KeyCode::Esc => session.focus = Focus::Results,
+                                KeyCode::F(5) => tui.submit_query(&text, false),
+                                KeyCode::F(6) => tui.submit_query(&text, true),
+                                KeyCode::F(7) => {
+                                    if let Some(query) = trim_query(&text) {
+                                        tui.run(query, true, true);
+                                    }
+                                }
+                                KeyCode::Char('r') if ctrl => tui.open_picker(),
+                                _ if run => tui.submit_query(&text, false),
+                                _ => session.editor.handle_key(key),
+                            }
+                            continue;
+                        }
+                        Focus::Catalog => {
+                            match (key.code, session.catalog.as_mut()) {
+                                (KeyCode::Esc, _) => session.focus = Focus::Results,
+                                (_, Some(catalog)) => {
+                                    let action = catalog.handle_key(key);
+                                    tui.catalog_action(action);
+                                }
+                                (_, None) => {}
+                            }
+                            continue;
+                        }
+                        Focus::Results => {}
+                    }
+                }
+                if let Some(popup) = tui.popup.as_mut() {
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => tui.popup = None,
+                        KeyCode::Char('j') | KeyCode::Down => {
+                            popup.scroll = popup.scroll.saturating_add(1)
+                        }
+                        KeyCode::Char('k') | KeyCode::Up => {
+                            popup.scroll = popup.scroll.saturating_sub(1)
+                        }
+                        KeyCode::Char('J') | KeyCode::PageDown => {
+                            popup.scroll = popup.scroll.saturating_add(20)
+                        }
+                        KeyCode::Char('K') | KeyCode::PageUp => {
+                            popup.scroll = popup.scroll.saturating_sub(20)
+                        }
+                        _ => {}
                     }
-                    (KeyCode::Char('k') | KeyCode::Up, KeyModifiers::NONE) => {
-                        tui.vertical_scroll = tui.vertical_scroll.saturating_sub(1);
+                    continue;
+                }
+                let tab = &mut tui.tabs[tui.current];
+                let (grid, view) = (&tab.grid, &mut tab.view);
+                let page = view.page();
+                match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Char('j') | KeyCode::Down if !shift => view.move_by(grid, 1, 0),
+                    KeyCode::Char('k') | KeyCode::Up if !shift => view.move_by(grid, -1, 0),
+                    KeyCode::Char('h') | KeyCode::Left if !shift => view.move_by(grid, 0, -1),
+                    KeyCode::Char('l') | KeyCode::Right if !shift => view.move_by(grid, 0, 1),
+                    KeyCode::Char('J') | KeyCode::Down | KeyCode::PageDown => {
+                        view.move_by(grid, page, 0)
                     }
-                    (KeyCode::Char('h') | KeyCode::Left, KeyModifiers::NONE) => {
-                        tui.horizontal_scroll = tui.horizontal_scroll.saturating_sub(1);
+                    KeyCode::Char('K') | KeyCode::Up | KeyCode::PageUp => {
+                        view.move_by(grid, -page, 0)
                     }
-                    (KeyCode::Char('l') | KeyCode::Right, KeyModifiers::NONE) => {
-                        tui.horizontal_scroll = tui.horizontal_scroll.saturating_add(1);
+                    KeyCode::Char('H') | KeyCode::Left => view.move_by(grid, 0, isize::MIN),
+                    KeyCode::Char('L') | KeyCode::Right => view.move_by(grid, 0, isize::MAX),
+                    KeyCode::Char('g') | KeyCode::Home => view.move_by(grid, isize::MIN, 0),
+                    KeyCode::Char('G') | KeyCode::End => view.move_by(grid, isize::MAX, 0),
+                    KeyCode::Char('f') => view.toggle_freeze(),
+                    KeyCode::Char('F') => tab.fetch_all = true,
+                    KeyCode::Enter => tui.open_popup(),
+                    KeyCode::Char('/') => tui.open_prompt(PromptTarget::Search),
+                    KeyCode::Char('&') => {
+                        let col = tab.view.col;
+                        tui.open_prompt(PromptTarget::Filter(col))
                     }
-                    (KeyCode::Char('j') | KeyCode::Down, KeyModifiers::SHIFT) => {
-                        tui.vertical_scroll = tui.vertical_scroll.saturating_add(20);
+                    KeyCode::Char('n') => tui.jump(true),
+                    KeyCode::Char('N') => tui.jump(false),
+                    KeyCode::Char('s') => tui.sort(false),
+                    KeyCode::Char('S') => tui.sort(true),
+                    KeyCode::Char('-') => tab.hide_column(),
+                    KeyCode::Char('+') => tab.unhide_column(),
+                    KeyCode::Char('<') => tab.move_column(-1),
+                    KeyCode::Char('>') => tab.move_column(1),
+                    KeyCode::Char(c @ '1'..='9') => {
+                        let index = c as usize - '1' as usize;
+                        if index < tui.tabs.len() {
+                            tui.current = index;
+                        }
                     }
-                    (KeyCode::Char('k') | KeyCode::Up, KeyModifiers::SHIFT) => {
-                        tui.vertical_scroll = tui.vertical_scroll.saturating_sub(20);
+                    KeyCode::Char('v') => {
+                        view.toggle_selection(Selection::Cells(view.row, view.col))
                     }
-                    (KeyCode::Char('h') | KeyCode::Left, KeyModifiers::SHIFT) => {
-                        tui.horizontal_scroll = tui.horizontal_scroll.saturating_sub(20);
+                    KeyCode::Char('V') => view.toggle_selection(Selection::Rows(view.row)),
+                    KeyCode::Char('c') => view.toggle_selection(Selection::Columns(view.col)),
+                    KeyCode::Char('y') => tui.yank(),
+                    KeyCode::Char('Y') => {
+                        tui.yank_format = tui.yank_format.next();
+                        tui.message = Some(format!("Yank as {}", tui.yank_format.name()));
                     }
-                    (KeyCode::Char('l') | KeyCode::Right, KeyModifiers::SHIFT) => {
-                        tui.horizontal_scroll = tui.horizontal_scroll.saturating_add(20);
+                    KeyCode::Char('w') => tui.open_prompt(PromptTarget::Save),
+                    KeyCode::Char('x') => tui.close_tab(),
+                    KeyCode::Char('D') => tui.show_schema(),
+                    KeyCode::Char('r') if ctrl => tui.open_picker(),
+                    KeyCode::Char('e') => tui.toggle_pane(Focus::Editor, true),
+                    KeyCode::Char('E') => tui.toggle_pane(Focus::Editor, false),
+                    KeyCode::Char('t') => tui.toggle_pane(Focus::Catalog, true),
+                    KeyCode::Char('T') => tui.toggle_pane(Focus::Catalog, false),
+                    KeyCode::Esc => {
+                        tab.search = None;
+                        tab.view.selection = None;
+                        if let Some(session) = tui.session.as_mut() {
+                            session.error = None;
+                        }
                     }
                     _ => {}
                 }
@@ -95,11 +771,229 @@ fn run_tui<B: Backend>(
     }
 }
 
-fn ui(f: &mut Frame, tui: &Tui, text: &str) {
-    let area = f.size();
+fn ui(f: &mut Frame, tui: &mut Tui) {
+    let [main_area, status_area] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(f.size());
+    let mut grid_area = main_area;
+    if let Some(session) = tui.session.as_mut() {
+        let mut area = main_area;
+        if session.catalog_shown {
+            let [catalog_area, rest] = Layout::horizontal([
+                Constraint::Length((area.width / 3).min(40)),
+                Constraint::Min(1),
+            ])
+            .areas(area);
+            let focused = session.focus == Focus::Catalog;
+            match session.catalog.as_mut() {
+                Some(catalog) => catalog.render(f, catalog_area, focused),
+                None => f.render_widget(
+                    Paragraph::new("Loading...").block(Block::bordered().title(" Tables ")),
+                    catalog_area,
+                ),
+            }
+            area = rest;
+        }
+        let editor_height = if session.editor_shown {
+            (session.editor.num_lines() as u16 + 2)
+                .max(4)
+                .min(area.height / 2)
+        } else {
+            0
+        };
+        let error_height = session.error.as_ref().map_or(0, |error| {
+            (error.lines().count() as u16 + 2).min(area.height / 4)
+        });
+        let [editor_area, error_area, rest] = Layout::vertical([
+            Constraint::Length(editor_height),
+            Constraint::Length(error_height),
+            Constraint::Min(1),
+        ])
+        .areas(area);
+        if session.editor_shown {
+            let focused = session.focus == Focus::Editor && session.picker.is_none();
+            session.editor.render(f, editor_area, focused);
+        }
+        if let Some(error) = &session.error {
+            let paragraph = Paragraph::new(error.as_str())
+                .red()
+                .block(Block::bordered().title(" Error "))
+                .wrap(Wrap { trim: false });
+            f.render_widget(paragraph, error_area);
+        }
+        grid_area = rest;
+    }
+    if tui.tabs.len() > 1 {
+        let [tabs_area, rest] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).areas(grid_area);
+        let titles = tui.tabs.iter().enumerate().map(|(index, tab)| {
+            let loading = if tab.is_loading() { "+" } else { "" };
+            format!("{} {}{}", index + 1, tab.title, loading)
+        });
+        let tabs = Tabs::new(titles)
+            .select(tui.current)
+            .highlight_style(Style::new().yellow().reversed())
+            .padding("", "")
+            .divider(" │ ");
+        f.render_widget(tabs, tabs_area);
+        grid_area = rest;
+    }
+
+    let tab = &mut tui.tabs[tui.current];
+    tab.view
+        .render(f, grid_area, &tab.grid, tab.search.as_ref());
+    match &tui.prompt {
+        Some(prompt) => {
+            let label = match prompt.target {
+                PromptTarget::Search => "/".to_string(),
+                PromptTarget::Filter(col) => format!("& {}: ", tab.grid.field(col).name()),
+                PromptTarget::Save => "save to (path [format] [mode]): ".to_string(),
+            };
+            let mode = if prompt.regex { "regex " } else { "" };
+            let line = format!("{}{}{}", mode, label, prompt.input);
+            f.set_cursor(status_area.x + line.width() as u16, status_area.y);
+            f.render_widget(Paragraph::new(line), status_area);
+        }
+        None => match &tui.message {
+            Some(message) => f.render_widget(Paragraph::new(message.as_str()).red(), status_area),
+            None => f.render_widget(status_bar(tui), status_area),
+        },
+    }
+
+    if let Some(popup) = &tui.popup {
+        let area = centered(f.size(), 80, 80);
+        let paragraph = Paragraph::new(popup.text.as_str())
+            .block(Block::bordered().title(popup.title.as_str()))
+            .wrap(Wrap { trim: false })
+            .scroll((popup.scroll, 0));
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
+    if let Some(session) = tui.session.as_mut() {
+        if let Some(picker) = session.picker.as_mut() {
+            picker.render(f, centered(f.size(), 80, 60), session.editor.history());
+        }
+    }
+}
+
+/// Cursor position, column type and key help
+fn status_bar(tui: &Tui) -> Paragraph<'_> {
+    let tab = tui.tab();
+    let (grid, view) = (&tab.grid, &tab.view);
+    let loading = match (tab.is_loading(), tab.fetch_all) {
+        (true, true) => "+ fetching",
+        (true, false) => "+",
+        (false, _) => "",
+    };
+    if let Some(session) = &tui.session {
+        let (position, help) = match session.focus {
+            _ if session.picker.is_some() => {
+                let help = "select up down  edit enter  run ctrl+enter/F5  new tab F6  close esc ";
+                (" history".to_string(), help)
+            }
+            Focus::Editor => {
+                let (line, col) = session.editor.position();
+                let position =
+                    format!(" line {}/{}  col {}", line, session.editor.num_lines(), col);
+                let help = "run ctrl+enter/F5  new tab F6  explain F7  history ctrl+p ctrl+n \
+                    ctrl+r  clear ctrl+u  results esc ";
+                (position, help)
+            }
+            Focus::Catalog => {
+                let help = "move jk  expand l enter  collapse h  insert column i  preview p  \
+                    results esc ";
+                (" tables".to_string(), help)
+            }
+            Focus::Results => (String::new(), ""),
+        };
+        if !help.is_empty() {
+            return Paragraph::new(Line::from(vec![
+                Span::raw(position),
+                Span::raw("  "),
+                Span::raw(help).dark_gray(),
+            ]))
+            .reversed();
+        }
+    }
+    let mut position = format!(
+        " row {}/{}{}  col {}/{}",
+        (view.row + 1).min(grid.num_rows()),
+        grid.num_rows(),
+        loading,
+        (view.col + 1).min(grid.num_columns()),
+        grid.num_columns()
+    );
+    if view.col < grid.num_columns() {
+        let field = grid.field(view.col);
+        position.push_str(&format!(
+            "  {}: {}",
+            field.name(),
+            type_name(field.data_type())
+        ));
+    }
+    if view.frozen > 0 {
+        position.push_str(&format!("  frozen {}", view.frozen));
+    }
+    if let Some((rows, cols)) = view.selected(grid) {
+        position.push_str(&format!("  selected {}x{}", rows.len(), cols.len()));
+    }
+    if let Some(search) = &tab.search {
+        let current = search
+            .position((view.row, view.col))
+            .map_or("-".to_string(), |index| (index + 1).to_string());
+        position.push_str(&format!(
+            "  /{} {}/{}",
+            search.pattern().input(),
+            current,
+            search.len()
+        ));
+    }
+    let sort: Vec<String> = grid
+        .sort_keys()
+        .map(|(name, descending)| format!("{}{}", name, if descending { "▼" } else { "▲" }))
+        .collect();
+    if !sort.is_empty() {
+        position.push_str(&format!("  sort {}", sort.join(", ")));
+    }
+    let filters: Vec<String> = grid
+        .filters()
+        .map(|(name, pattern)| format!("{}~{}", name, pattern.input()))
+        .collect();
+    if !filters.is_empty() {
+        position.push_str(&format!(
+            "  filter {} ({} of {} rows)",
+            filters.join(", "),
+            grid.num_rows(),
+            grid.total_rows()
+        ));
+    }
+    let hidden: Vec<&str> = grid.hidden().collect();
+    if !hidden.is_empty() {
+        position.push_str(&format!("  hidden {}", hidden.join(", ")));
+    }
+    let help = "move hjkl/arrows  page JK  first/last gG HL  freeze f  fetch all F  value enter  \
+        search / n N  filter &  sort s S  hide - +  move < >  select v V c  yank y Y  save w  \
+        edit e E  tables t T  tabs 1-9 x  schema D  history ctrl+r  quit q ";
+    Paragraph::new(Line::from(vec![
+        Span::raw(position),
+        Span::raw("  "),
+        Span::raw(help).dark_gray(),
+    ]))
+    .reversed()
+}
 
-    let paragraph = Paragraph::new(text)
-        .gray()
-        .scroll((tui.vertical_scroll as u16, tui.horizontal_scroll as u16));
-    f.render_widget(paragraph, area);
+/// Rect of `percent_x` by `percent_y` of `area` at its center
+fn centered(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let [_, area, _] = Layout::vertical([
+        Constraint::Percentage((100 - percent_y) / 2),
+        Constraint::Percentage(percent_y),
+        Constraint::Percentage((100 - percent_y) / 2),
+    ])
+    .areas(area);
+    let [_, area, _] = Layout::horizontal([
+        Constraint::Percentage((100 - percent_x) / 2),
+        Constraint::Percentage(percent_x),
+        Constraint::Percentage((100 - percent_x) / 2),
+    ])
+    .areas(area);
+    area
 }
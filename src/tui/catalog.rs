@@ -0,0 +1,282 @@
+use arrow::datatypes::{DataType, Field};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{prelude::*, widgets::*};
+
+use super::grid::type_name;
+use crate::table::{Format, TableInfo};
+
+/// What a key pressed in the catalog asks for
+pub enum Action {
+    None,
+    /// insert a column in the query
+    Insert(String),
+    /// show the first rows of a table
+    Preview(String),
+}
+
+/// Line of the catalog tree
+struct Node {
+    label: String,
+    /// type or value shown after the label
+    detail: String,
+    children: Vec<Node>,
+    expanded: bool,
+    /// column expression inserted in the query
+    column: Option<String>,
+}
+
+impl Node {
+    fn new(label: &str, detail: String) -> Self {
+        Self {
+            label: label.to_string(),
+            detail,
+            children: Vec::new(),
+            expanded: false,
+            column: None,
+        }
+    }
+
+    /// Column with its nested fields, `column` being the expression selecting it
+    fn column(field: &Field, column: String, partitions: &[String]) -> Self {
+        let mut detail = match field.data_type() {
+            DataType::Struct(_) => "Struct".to_string(),
+            DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(_, _) => {
+                "List".to_string()
+            }
+            DataType::Map(_, _) => "Map".to_string(),
+            data_type => type_name(data_type),
+        };
+        if !field.is_nullable() {
+            detail.push_str(" not null");
+        }
+        if partitions.contains(field.name()) {
+            detail.push_str(" partition");
+        }
+        let children = match field.data_type() {
+            DataType::Struct(fields) => fields
+                .iter()
+                .map(|child| {
+                    let expr = format!("{}['{}']", column, child.name().replace('\'', "''"));
+                    Node::column(child, expr, &[])
+                })
+                .collect(),
+            DataType::List(item) | DataType::LargeList(item) | DataType::FixedSizeList(item, _) => {
+                vec![Node::column(item, column.clone(), &[])]
+            }
+            DataType::Map(entries, _) => match entries.data_type() {
+                DataType::Struct(fields) => fields
+                    .iter()
+                    .map(|child| Node::column(child, column.clone(), &[]))
+                    .collect(),
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+        Self {
+            children,
+            column: Some(column),
+            ..Self::new(field.name(), detail)
+        }
+    }
+
+    fn table(info: &TableInfo) -> Self {
+        let format = info.format.map(Format::name).unwrap_or_default();
+        let detail = match info.version {
+            Some(version) => format!("{} v{}", format, version),
+            None => format.to_string(),
+        };
+        let mut node = Node::new(&info.name, detail);
+        node.expanded = true;
+        node.children
+            .push(Node::new("path", info.path.as_str().to_string()));
+        if !info.partitions.is_empty() {
+            node.children
+                .push(Node::new("partitions", info.partitions.join(", ")));
+        }
+        for field in info.schema.fields() {
+            let column = Node::column(field, quote_identifier(field.name()), &info.partitions);
+            node.children.push(column);
+        }
+        if !info.properties.is_empty() {
+            let mut properties = Node::new("properties", info.properties.len().to_string());
+            properties.children = info
+                .properties
+                .iter()
+                .map(|(key, value)| Node::new(key, value.clone()))
+                .collect();
+            node.children.push(properties);
+        }
+        node
+    }
+}
+
+/// Column name as written in a query, quoted unless lower case
+fn quote_identifier(name: &str) -> String {
+    let plain = name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+/// Registered tables as a tree of their columns and metadata
+pub struct Catalog {
+    tables: Vec<Node>,
+    /// shown nodes, as child indexes from the tables
+    visible: Vec<Vec<usize>>,
+    cursor: usize,
+    offset: usize,
+}
+
+impl Catalog {
+    pub fn new(infos: &[TableInfo]) -> Self {
+        let mut catalog = Self {
+            tables: infos.iter().map(Node::table).collect(),
+            visible: Vec::new(),
+            cursor: 0,
+            offset: 0,
+        };
+        catalog.update_visible();
+        catalog
+    }
+
+    fn update_visible(&mut self) {
+        fn visit(node: &Node, path: &mut Vec<usize>, visible: &mut Vec<Vec<usize>>) {
+            visible.push(path.clone());
+            if node.expanded {
+                for (index, child) in node.children.iter().enumerate() {
+                    path.push(index);
+                    visit(child, path, visible);
+                    path.pop();
+                }
+            }
+        }
+        self.visible.clear();
+        for (index, table) in self.tables.iter().enumerate() {
+            visit(table, &mut vec![index], &mut self.visible);
+        }
+        self.cursor = self.cursor.min(self.visible.len().saturating_sub(1));
+    }
+
+    fn node(&self, path: &[usize]) -> &Node {
+        let table = &self.tables[path[0]];
+        path[1..]
+            .iter()
+            .fold(table, |node, index| &node.children[*index])
+    }
+
+    fn node_mut(&mut self, path: &[usize]) -> &mut Node {
+        let table = &mut self.tables[path[0]];
+        path[1..]
+            .iter()
+            .fold(table, |node, index| &mut node.children[*index])
+    }
+
+    /// Expand or collapse the node under the cursor
+    fn set_expanded(&mut self, expanded: bool) {
+        let Some(path) = self.visible.get(self.cursor).cloned() else {
+            return;
+        };
+        let node = self.node_mut(&path);
+        if node.children.is_empty() || node.expanded == expanded {
+            // collapsing a leaf or a collapsed node goes to its parent
+            if !expanded && path.len() > 1 {
+                let parent = &path[..path.len() - 1];
+                self.cursor = self.visible.iter().position(|p| p == parent).unwrap_or(0);
+            }
+            return;
+        }
+        node.expanded = expanded;
+        self.update_visible();
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let last = self.visible.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => self.cursor = (self.cursor + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Char('g') | KeyCode::Home => self.cursor = 0,
+            KeyCode::Char('G') | KeyCode::End => self.cursor = last,
+            KeyCode::Char('l') | KeyCode::Right => self.set_expanded(true),
+            KeyCode::Char('h') | KeyCode::Left => self.set_expanded(false),
+            KeyCode::Enter => {
+                let expanded = self
+                    .visible
+                    .get(self.cursor)
+                    .is_some_and(|path| self.node(path).expanded);
+                self.set_expanded(!expanded);
+            }
+            KeyCode::Char('i') => {
+                if let Some(column) = self
+                    .visible
+                    .get(self.cursor)
+                    .and_then(|path| self.node(path).column.clone())
+                {
+                    return Action::Insert(column);
+                }
+            }
+            KeyCode::Char('p') => {
+                if let Some(path) = self.visible.get(self.cursor) {
+                    return Action::Preview(self.tables[path[0]].label.clone());
+                }
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// Tree scrolled to the cursor, highlighted when focused
+    pub fn render(&mut self, f: &mut Frame, area: Rect, focused: bool) {
+        let border_style = if focused {
+            Style::new().yellow()
+        } else {
+            Style::new().dark_gray()
+        };
+        let block = Block::bordered()
+            .title(" Tables ")
+            .border_style(border_style);
+        let height = block.inner(area).height.max(1) as usize;
+        self.offset = self
+            .offset
+            .min(self.cursor)
+            .max((self.cursor + 1).saturating_sub(height));
+
+        let lines: Vec<Line> = self
+            .visible
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(height)
+            .map(|(index, path)| {
+                let node = self.node(path);
+                let marker = match (node.children.is_empty(), node.expanded) {
+                    (true, _) => "  ",
+                    (false, true) => "▾ ",
+                    (false, false) => "▸ ",
+                };
+                let label_style = if path.len() == 1 {
+                    Style::new().bold()
+                } else {
+                    Style::new()
+                };
+                let line = Line::from(vec![
+                    Span::raw("  ".repeat(path.len() - 1)),
+                    Span::raw(marker),
+                    Span::styled(node.label.as_str(), label_style),
+                    Span::raw(" "),
+                    Span::raw(node.detail.as_str()).dark_gray(),
+                ]);
+                match (index == self.cursor, focused) {
+                    (true, true) => line.reversed(),
+                    (true, false) => line.on_dark_gray(),
+                    (false, _) => line,
+                }
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::{self, Write};
+
+use arrow::csv::WriterBuilder;
+use arrow::error::ArrowError;
+use arrow::json::{writer::JsonArray, WriterBuilder as JsonWriterBuilder};
+use arrow::record_batch::RecordBatch;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use super::grid::cell_value;
+
+/// Text form of the yanked cells
+#[derive(Clone, Copy, Default)]
+pub enum YankFormat {
+    /// pasted as cells in spreadsheets
+    #[default]
+    Tsv,
+    Csv,
+    /// array of objects
+    Json,
+    Markdown,
+}
+
+impl YankFormat {
+    pub fn next(self) -> Self {
+        match self {
+            YankFormat::Tsv => YankFormat::Csv,
+            YankFormat::Csv => YankFormat::Json,
+            YankFormat::Json => YankFormat::Markdown,
+            YankFormat::Markdown => YankFormat::Tsv,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            YankFormat::Tsv => "tsv",
+            YankFormat::Csv => "csv",
+            YankFormat::Json => "json",
+            YankFormat::Markdown => "markdown",
+        }
+    }
+
+    /// Rows of `batch` along with their header
+    pub fn format(self, batch: &RecordBatch) -> Result<String, ArrowError> {
+        let mut buffer = Vec::new();
+        match self {
+            YankFormat::Tsv | YankFormat::Csv => {
+                let delimiter = if matches!(self, YankFormat::Tsv) {
+                    b'\t'
+                } else {
+                    b','
+                };
+                let mut writer = WriterBuilder::new()
+                    .with_delimiter(delimiter)
+                    .build(&mut buffer);
+                writer.write(batch)?;
+            }
+            YankFormat::Json => {
+                let mut writer = JsonWriterBuilder::new()
+                    .with_explicit_nulls(true)
+                    .build::<_, JsonArray>(&mut buffer);
+                writer.write(batch)?;
+                writer.finish()?;
+            }
+            YankFormat::Markdown => return Ok(markdown(batch)),
+        }
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+/// Markdown table, the nulls being empty cells
+fn markdown(batch: &RecordBatch) -> String {
+    let escape = |value: &str| value.replace('|', "\\|").replace(['\n', '\r'], " ");
+    let schema = batch.schema();
+    let header: Vec<String> = schema
+        .fields()
+        .iter()
+        .map(|field| escape(field.name()))
+        .collect();
+    let mut lines = vec![
+        format!("| {} |", header.join(" | ")),
+        format!("|{}|", vec!["---"; header.len()].join("|")),
+    ];
+    for row in 0..batch.num_rows() {
+        let cells: Vec<String> = batch
+            .columns()
+            .iter()
+            .map(|column| escape(&cell_value(column, row).unwrap_or_default()))
+            .collect();
+        lines.push(format!("| {} |", cells.join(" | ")));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Put `text` in the terminal clipboard with an OSC 52 sequence, terminals
+/// not supporting it ignore the sequence
+pub fn copy(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
@@ -0,0 +1,329 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{prelude::*, widgets::*};
+use unicode_width::UnicodeWidthStr;
+
+/// Highlighted words, other ones being table, column or function names
+const KEYWORDS: &str = "\
+    all and as asc between by case cast columns create cross desc describe distinct \
+    else end except exists explain external false from full group having ilike in inner \
+    intersect is join left like limit not null offset on or order outer over partition \
+    right select show table tables then true union using values when where with";
+
+/// Multi-line SQL text with a cursor, along with the queries run from it
+pub struct Editor {
+    lines: Vec<String>,
+    /// cursor line and char index in it
+    row: usize,
+    col: usize,
+    row_offset: usize,
+    col_offset: usize,
+    history: Vec<String>,
+    /// history entry shown in place of the edited text
+    recalled: Option<usize>,
+    /// edited text, kept while the history is browsed
+    draft: Vec<String>,
+}
+
+impl Editor {
+    pub fn new(text: &str) -> Self {
+        let mut editor = Self {
+            lines: Vec::new(),
+            row: 0,
+            col: 0,
+            row_offset: 0,
+            col_offset: 0,
+            history: Vec::new(),
+            recalled: None,
+            draft: Vec::new(),
+        };
+        editor.set_text(text);
+        editor
+    }
+
+    /// Editor recalling the queries of `history`, the most recent last
+    pub fn with_history(mut self, history: Vec<String>) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// Replace the text, the cursor going to its end
+    pub fn set_text(&mut self, text: &str) {
+        self.lines = text.lines().map(str::to_string).collect();
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        self.row = self.lines.len() - 1;
+        self.col = self.line_len();
+    }
+
+    /// Cursor line and column, starting at 1
+    pub fn position(&self) -> (usize, usize) {
+        (self.row + 1, self.col + 1)
+    }
+
+    pub fn num_lines(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Remember a query being run, returns whether it differs from the last
+    /// one
+    pub fn push_history(&mut self, query: String) -> bool {
+        self.recalled = None;
+        if self.history.last() == Some(&query) {
+            return false;
+        }
+        self.history.push(query);
+        true
+    }
+
+    /// Show the previous query of the history, or the next one
+    fn recall(&mut self, previous: bool) {
+        let index = match (self.recalled, previous) {
+            (None, true) => self.history.len().checked_sub(1),
+            (None, false) => return,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) => Some(index + 1).filter(|index| *index < self.history.len()),
+        };
+        if self.recalled.is_none() {
+            self.draft = self.lines.clone();
+        }
+        let text = match index {
+            Some(index) => self.history[index].clone(),
+            None => self.draft.join("\n"),
+        };
+        self.recalled = index;
+        self.set_text(&text);
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    /// Byte index of the cursor in its line
+    fn byte_index(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(self.col)
+            .map_or(line.len(), |(index, _)| index)
+    }
+
+    /// Insert `text` at the cursor
+    pub fn insert(&mut self, text: &str) {
+        let index = self.byte_index();
+        self.lines[self.row].insert_str(index, text);
+        self.col += text.chars().count();
+    }
+
+    /// Split the line at the cursor, the new line keeping the indentation
+    fn newline(&mut self) {
+        let index = self.byte_index();
+        let rest = self.lines[self.row].split_off(index);
+        let indent: String = self.lines[self.row]
+            .chars()
+            .take_while(|c| *c == ' ')
+            .collect();
+        self.col = indent.chars().count();
+        self.row += 1;
+        self.lines.insert(self.row, indent + &rest);
+    }
+
+    fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let index = self.byte_index();
+            self.lines[self.row].remove(index);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len();
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.col < self.line_len() {
+            let index = self.byte_index();
+            self.lines[self.row].remove(index);
+        } else if self.row + 1 < self.lines.len() {
+            let line = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    fn move_to(&mut self, row: usize, col: usize) {
+        self.row = row.min(self.lines.len() - 1);
+        self.col = col.min(self.line_len());
+    }
+
+    /// Edit the text or move the cursor, the keys running the query being
+    /// handled by the caller
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('p') if ctrl => self.recall(true),
+            KeyCode::Char('n') if ctrl => self.recall(false),
+            KeyCode::Char('u') if ctrl => self.set_text(""),
+            KeyCode::Char(_) if ctrl => {}
+            KeyCode::Char(c) => self.insert(c.encode_utf8(&mut [0; 4])),
+            KeyCode::Tab => self.insert("    "),
+            KeyCode::Enter => self.newline(),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left if self.col == 0 && self.row > 0 => {
+                self.move_to(self.row - 1, usize::MAX)
+            }
+            KeyCode::Left => self.col -= self.col.min(1),
+            KeyCode::Right if self.col == self.line_len() => self.move_to(self.row + 1, 0),
+            KeyCode::Right => self.col += 1,
+            KeyCode::Up => self.move_to(self.row.saturating_sub(1), self.col),
+            KeyCode::Down => self.move_to(self.row + 1, self.col),
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = self.line_len(),
+            _ => {}
+        }
+    }
+
+    /// Highlighted text scrolled to the cursor, which is shown when focused
+    pub fn render(&mut self, f: &mut Frame, area: Rect, focused: bool) {
+        let border_style = if focused {
+            Style::new().yellow()
+        } else {
+            Style::new().dark_gray()
+        };
+        let block = Block::bordered()
+            .title(" Query ")
+            .border_style(border_style);
+        let inner = block.inner(area);
+        let (height, width) = (inner.height.max(1) as usize, inner.width.max(1) as usize);
+        self.row_offset = self
+            .row_offset
+            .min(self.row)
+            .max((self.row + 1).saturating_sub(height));
+        self.col_offset = self
+            .col_offset
+            .min(self.col)
+            .max((self.col + 1).saturating_sub(width));
+
+        let lines: Vec<Line> = highlight(&self.lines)
+            .into_iter()
+            .skip(self.row_offset)
+            .take(height)
+            .map(|tokens| scroll(tokens, self.col_offset))
+            .collect();
+        f.render_widget(Paragraph::new(lines).block(block), area);
+        if focused {
+            let before: String = self.lines[self.row]
+                .chars()
+                .skip(self.col_offset)
+                .take(self.col - self.col_offset)
+                .collect();
+            f.set_cursor(
+                inner.x + before.width() as u16,
+                inner.y + (self.row - self.row_offset) as u16,
+            );
+        }
+    }
+}
+
+/// Line of tokens without its first `offset` chars
+fn scroll(tokens: Vec<(String, Style)>, offset: usize) -> Line<'static> {
+    let mut skipped = 0;
+    let spans: Vec<Span> = tokens
+        .into_iter()
+        .filter_map(|(text, style)| {
+            let len = text.chars().count();
+            let skip = offset.saturating_sub(skipped).min(len);
+            skipped += len;
+            (skip < len).then(|| Span::styled(text.chars().skip(skip).collect::<String>(), style))
+        })
+        .collect();
+    Line::from(spans)
+}
+
+/// What the end of a line is part of
+#[derive(Clone, Copy, PartialEq)]
+enum Context {
+    Code,
+    /// string or quoted identifier
+    Quoted(char),
+    Comment,
+}
+
+/// Styled tokens of each line, strings and block comments spanning lines
+fn highlight(lines: &[String]) -> Vec<Vec<(String, Style)>> {
+    let keyword = Style::new().blue().bold();
+    let string = Style::new().green();
+    let identifier = Style::new().yellow();
+    let number = Style::new().cyan();
+    let comment = Style::new().dark_gray().italic();
+
+    let mut context = Context::Code;
+    let mut highlighted = Vec::with_capacity(lines.len());
+    for line in lines {
+        let chars: Vec<char> = line.chars().collect();
+        let mut tokens = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let rest = &chars[start..];
+            let (len, style) = match context {
+                Context::Comment => {
+                    let end = rest.windows(2).position(|w| w == ['*', '/']);
+                    if end.is_some() {
+                        context = Context::Code;
+                    }
+                    (end.map_or(rest.len(), |end| end + 2), comment)
+                }
+                Context::Quoted(quote) => {
+                    let end = rest.iter().position(|c| *c == quote);
+                    if end.is_some() {
+                        context = Context::Code;
+                    }
+                    let style = if quote == '\'' { string } else { identifier };
+                    (end.map_or(rest.len(), |end| end + 1), style)
+                }
+                Context::Code => match rest {
+                    ['-', '-', ..] => (rest.len(), comment),
+                    ['/', '*', ..] => {
+                        context = Context::Comment;
+                        (2, comment)
+                    }
+                    [quote @ ('\'' | '"'), ..] => {
+                        context = Context::Quoted(*quote);
+                        (1, if *quote == '\'' { string } else { identifier })
+                    }
+                    [c, ..] if c.is_alphabetic() || *c == '_' => {
+                        let len = rest
+                            .iter()
+                            .position(|c| !c.is_alphanumeric() && *c != '_')
+                            .unwrap_or(rest.len());
+                        let word: String = rest[..len].iter().collect();
+                        let is_keyword = KEYWORDS
+                            .split_whitespace()
+                            .any(|keyword| keyword.eq_ignore_ascii_case(&word));
+                        (len, if is_keyword { keyword } else { Style::new() })
+                    }
+                    [c, ..] if c.is_ascii_digit() => {
+                        let len = rest
+                            .iter()
+                            .position(|c| !c.is_ascii_digit() && *c != '.')
+                            .unwrap_or(rest.len());
+                        (len, number)
+                    }
+                    _ => (1, Style::new()),
+                },
+            };
+            tokens.push((chars[start..start + len].iter().collect(), style));
+            start += len;
+        }
+        highlighted.push(tokens);
+    }
+    highlighted
+}
@@ -0,0 +1,855 @@
+use arrow::array::{new_empty_array, Array, ArrayRef, UInt64Array};
+use arrow::compute::{concat, take, SortOptions};
+use arrow::datatypes::{DataType, Field, FieldRef, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::json::ArrayWriter;
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use arrow::row::{RowConverter, Rows, SortField};
+use arrow::util::display::array_value_to_string;
+use ratatui::layout::Flex;
+use ratatui::{prelude::*, widgets::*};
+use std::mem::discriminant;
+use std::ops::Range;
+use std::sync::Arc;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use super::search::{Pattern, Search};
+
+/// Column widths are computed from the first rows, later rows are truncated
+pub const WIDTH_SAMPLE_ROWS: usize = 1000;
+/// Longer values are truncated, the cell popup shows them in full
+const MAX_COLUMN_WIDTH: usize = 40;
+
+/// Record batches shown as a single table.
+///
+/// Shown rows and columns are indexes in the received ones: rows are sorted
+/// and filtered, columns are reordered and hidden.
+pub struct Grid {
+    schema: SchemaRef,
+    batches: Vec<RecordBatch>,
+    /// index of the first row of each batch
+    offsets: Vec<usize>,
+    total_rows: usize,
+    widths: Vec<usize>,
+    /// shown rows, the ones matching every column filter in the sort order
+    rows: Vec<usize>,
+    /// shown columns
+    columns: Vec<usize>,
+    /// hidden columns with their shown index, the last hidden one first
+    /// shown again
+    hidden: Vec<(usize, usize)>,
+    filters: Vec<(usize, Pattern)>,
+    /// sort keys, the descending ones flagged
+    sort: Vec<(usize, bool)>,
+    /// values of the sort keys, for the received rows to be merged in order
+    sort_keys: Option<SortKeys>,
+}
+
+/// Sort key values of the received rows in the arrow row format, compared as
+/// bytes
+struct SortKeys {
+    converter: RowConverter,
+    /// keys of the rows of each batch
+    rows: Vec<Rows>,
+}
+
+impl SortKeys {
+    fn new(schema: &Schema, sort: &[(usize, bool)]) -> Result<Self, ArrowError> {
+        let fields = sort
+            .iter()
+            .map(|(col, descending)| {
+                let options = SortOptions {
+                    descending: *descending,
+                    nulls_first: false,
+                };
+                SortField::new_with_options(schema.field(*col).data_type().clone(), options)
+            })
+            .collect();
+        Ok(Self {
+            converter: RowConverter::new(fields)?,
+            rows: Vec::new(),
+        })
+    }
+
+    fn push(&mut self, batch: &RecordBatch, sort: &[(usize, bool)]) -> Result<(), ArrowError> {
+        let columns: Vec<ArrayRef> = sort
+            .iter()
+            .map(|(col, _)| batch.column(*col).clone())
+            .collect();
+        self.rows.push(self.converter.convert_columns(&columns)?);
+        Ok(())
+    }
+
+    fn key(&self, (batch, index): (usize, usize)) -> arrow::row::Row<'_> {
+        self.rows[batch].row(index)
+    }
+}
+
+impl Grid {
+    pub fn new(schema: SchemaRef) -> Self {
+        let widths = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().width().min(MAX_COLUMN_WIDTH))
+            .collect();
+        Self {
+            columns: (0..schema.fields().len()).collect(),
+            schema,
+            batches: Vec::new(),
+            offsets: Vec::new(),
+            total_rows: 0,
+            widths,
+            rows: Vec::new(),
+            hidden: Vec::new(),
+            filters: Vec::new(),
+            sort: Vec::new(),
+            sort_keys: None,
+        }
+    }
+
+    /// Receive the rows of `batch`, returns whether shown rows moved down,
+    /// the kept ones being merged into the sort order
+    pub fn push(&mut self, batch: RecordBatch) -> bool {
+        if batch.num_rows() == 0 {
+            return false;
+        }
+        let sampled = WIDTH_SAMPLE_ROWS
+            .saturating_sub(self.total_rows)
+            .min(batch.num_rows());
+        for (column, width) in batch.columns().iter().zip(self.widths.iter_mut()) {
+            for row in 0..sampled {
+                let value_width = single_line(cell_value(column, row).as_deref()).width();
+                *width = (*width).max(value_width.min(MAX_COLUMN_WIDTH));
+            }
+        }
+        let first_row = self.total_rows;
+        self.offsets.push(first_row);
+        self.total_rows += batch.num_rows();
+        self.batches.push(batch);
+        let added: Vec<usize> = (first_row..self.total_rows)
+            .filter(|row| self.is_kept(*row))
+            .collect();
+        if self.sort.is_empty() {
+            self.rows.extend(added);
+            return false;
+        }
+        match self.merge_sorted(added) {
+            Ok(moved) => moved,
+            Err(added) => {
+                // the sort keys were already converted once, so it hardly happens
+                self.rows.extend(added);
+                false
+            }
+        }
+    }
+
+    /// Merge the `added` rows of the last batch into the sorted shown rows,
+    /// returns whether some of them are shown before already shown ones or
+    /// gives them back when their sort keys cannot be converted
+    fn merge_sorted(&mut self, mut added: Vec<usize>) -> Result<bool, Vec<usize>> {
+        let batch = self.batches.len() - 1;
+        let Some(keys) = self.sort_keys.as_mut() else {
+            return Err(added);
+        };
+        if keys.push(&self.batches[batch], &self.sort).is_err() {
+            return Err(added);
+        }
+        let rows = std::mem::take(&mut self.rows);
+        let keys = self.sort_keys.as_ref().expect("sort keys set above");
+        let key = |row: usize| keys.key(self.position(row));
+        added.sort_by(|a, b| key(*a).cmp(&key(*b)));
+        let mut merged = Vec::with_capacity(rows.len() + added.len());
+        let mut added = added.into_iter().peekable();
+        let mut moved = false;
+        for row in rows {
+            while let Some(new) = added.next_if(|new| key(*new) < key(row)) {
+                merged.push(new);
+                moved = true;
+            }
+            merged.push(row);
+        }
+        merged.extend(added);
+        self.rows = merged;
+        Ok(moved)
+    }
+
+    /// Number of shown rows
+    pub fn num_rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Number of received rows, filtered or not
+    pub fn total_rows(&self) -> usize {
+        self.total_rows
+    }
+
+    /// Number of shown columns
+    pub fn num_columns(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Field of a shown column
+    pub fn field(&self, col: usize) -> &FieldRef {
+        &self.schema.fields()[self.columns[col]]
+    }
+
+    /// Name of a shown column, with its sort direction
+    fn header(&self, col: usize) -> String {
+        let name = self.field(col).name();
+        match self.sort.iter().find(|(key, _)| *key == self.columns[col]) {
+            Some((_, true)) => format!("{}▼", name),
+            Some((_, false)) => format!("{}▲", name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Widths of the shown columns, wide enough for their header and the
+    /// values of the sampled rows and of the `rows` on screen
+    fn column_widths(&self, rows: Range<usize>) -> Vec<usize> {
+        let mut widths: Vec<usize> = (0..self.num_columns())
+            .map(|col| {
+                let header_width = self.header(col).width().min(MAX_COLUMN_WIDTH);
+                self.widths[self.columns[col]].max(header_width)
+            })
+            .collect();
+        for row in rows {
+            for (col, width) in widths.iter_mut().enumerate() {
+                let value_width = single_line(self.value(row, col).as_deref()).width();
+                *width = (*width).max(value_width.min(MAX_COLUMN_WIDTH));
+            }
+        }
+        widths
+    }
+
+    /// Batch holding the received `row` along with the row index in it
+    fn position(&self, row: usize) -> (usize, usize) {
+        let batch = self.offsets.partition_point(|offset| *offset <= row) - 1;
+        (batch, row - self.offsets[batch])
+    }
+
+    /// Column of the batch holding the received `row` along with the row
+    /// index in it
+    fn locate(&self, row: usize, col: usize) -> (&ArrayRef, usize) {
+        let (batch, index) = self.position(row);
+        (self.batches[batch].column(col), index)
+    }
+
+    /// Received row shown at `row`
+    pub fn record(&self, row: usize) -> Option<usize> {
+        self.rows.get(row).copied()
+    }
+
+    /// Index the received `record` is shown at, None when filtered out
+    pub fn shown_row(&self, record: usize) -> Option<usize> {
+        self.rows.iter().position(|row| *row == record)
+    }
+
+    /// Value of a shown cell, None when null
+    pub fn value(&self, row: usize, col: usize) -> Option<String> {
+        let (column, index) = self.locate(self.rows[row], self.columns[col]);
+        cell_value(column, index)
+    }
+
+    /// Column names and patterns of the filters
+    pub fn filters(&self) -> impl Iterator<Item = (&str, &Pattern)> {
+        self.filters
+            .iter()
+            .map(|(col, pattern)| (self.schema.field(*col).name().as_str(), pattern))
+    }
+
+    /// Only show the rows whose `col` value matches `pattern`, all of them
+    /// when there is no pattern
+    pub fn set_filter(&mut self, col: usize, pattern: Option<Pattern>) {
+        let col = self.columns[col];
+        self.filters.retain(|(filtered, _)| *filtered != col);
+        if let Some(pattern) = pattern {
+            self.filters.push((col, pattern));
+        }
+        // sorting already succeeded with the same keys
+        let _ = self.update_rows();
+    }
+
+    /// Whether the received `row` matches every filter
+    fn is_kept(&self, row: usize) -> bool {
+        self.filters.iter().all(|(col, pattern)| {
+            let (column, index) = self.locate(row, *col);
+            cell_value(column, index).is_some_and(|value| pattern.is_match(&value))
+        })
+    }
+
+    /// Column names and directions of the sort keys
+    pub fn sort_keys(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.sort
+            .iter()
+            .map(|(col, descending)| (self.schema.field(*col).name().as_str(), *descending))
+    }
+
+    /// Sort by `col`, ascending then descending then not at all. The other
+    /// sort keys are kept when `multi`, `col` being added as the last one.
+    pub fn toggle_sort(&mut self, col: usize, multi: bool) -> Result<(), ArrowError> {
+        let col = self.columns[col];
+        let current = self
+            .sort
+            .iter()
+            .find(|(key, _)| *key == col)
+            .map(|(_, descending)| *descending);
+        let previous = self.sort.clone();
+        if !multi {
+            self.sort.retain(|(key, _)| *key == col);
+        }
+        match current {
+            Some(false) => {
+                for key in self.sort.iter_mut().filter(|(key, _)| *key == col) {
+                    key.1 = true;
+                }
+            }
+            Some(true) => self.sort.retain(|(key, _)| *key != col),
+            None => self.sort.push((col, false)),
+        }
+        self.update_rows().inspect_err(|_| {
+            self.sort = previous;
+            let _ = self.update_rows();
+        })
+    }
+
+    /// Shown rows: the received ones matching the filters, in the sort order
+    fn update_rows(&mut self) -> Result<(), ArrowError> {
+        self.sort_keys = None;
+        let mut rows: Vec<usize> = (0..self.total_rows)
+            .filter(|row| self.is_kept(*row))
+            .collect();
+        if !self.sort.is_empty() {
+            let mut keys = SortKeys::new(&self.schema, &self.sort)?;
+            for batch in self.batches.iter() {
+                keys.push(batch, &self.sort)?;
+            }
+            rows.sort_by(|a, b| {
+                keys.key(self.position(*a))
+                    .cmp(&keys.key(self.position(*b)))
+            });
+            self.sort_keys = Some(keys);
+        }
+        self.rows = rows;
+        Ok(())
+    }
+
+    /// Hide a shown column
+    pub fn hide(&mut self, col: usize) {
+        if col < self.columns.len() {
+            self.hidden.push((self.columns.remove(col), col));
+        }
+    }
+
+    /// Show the last hidden column again where it was shown, returns its
+    /// shown index
+    pub fn unhide(&mut self) -> Option<usize> {
+        let (col, index) = self.hidden.pop()?;
+        let index = index.min(self.columns.len());
+        self.columns.insert(index, col);
+        Some(index)
+    }
+
+    /// Names of the hidden columns
+    pub fn hidden(&self) -> impl Iterator<Item = &str> {
+        self.hidden
+            .iter()
+            .map(|(col, _)| self.schema.field(*col).name().as_str())
+    }
+
+    /// Swap a shown column with its neighbour on the right, or on the left
+    /// when `offset` is negative, returns its new index
+    pub fn move_column(&mut self, col: usize, offset: isize) -> usize {
+        let target = col.saturating_add_signed(offset);
+        if target < self.columns.len() && col < self.columns.len() {
+            self.columns.swap(col, target);
+            target
+        } else {
+            col
+        }
+    }
+
+    /// Shown `rows` of the shown `cols` as a single batch, in the shown order
+    pub fn to_batch(
+        &self,
+        rows: Range<usize>,
+        cols: Range<usize>,
+    ) -> Result<RecordBatch, ArrowError> {
+        let indices = UInt64Array::from_iter_values(self.rows[rows].iter().map(|row| *row as u64));
+        let mut fields = Vec::with_capacity(cols.len());
+        let mut columns = Vec::with_capacity(cols.len());
+        for col in cols {
+            let field = self.field(col);
+            let arrays: Vec<&dyn Array> = self
+                .batches
+                .iter()
+                .map(|batch| batch.column(self.columns[col]).as_ref())
+                .collect();
+            let values = if arrays.is_empty() {
+                new_empty_array(field.data_type())
+            } else {
+                concat(&arrays)?
+            };
+            columns.push(take(&values, &indices, None)?);
+            fields.push(field.clone());
+        }
+        let options = RecordBatchOptions::new().with_row_count(Some(indices.len()));
+        RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), columns, &options)
+    }
+
+    /// Full value of a cell: nested values and json strings are pretty printed
+    pub fn detail(&self, row: usize, col: usize) -> String {
+        let (column, index) = self.locate(self.rows[row], self.columns[col]);
+        let Some(value) = cell_value(column, index) else {
+            return "null".to_string();
+        };
+        let json = match column.data_type() {
+            DataType::Struct(_)
+            | DataType::List(_)
+            | DataType::LargeList(_)
+            | DataType::FixedSizeList(..)
+            | DataType::Map(..) => nested_to_json(column, index),
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => {
+                serde_json::from_str::<serde_json::Value>(&value)
+                    .ok()
+                    .filter(|json| json.is_object() || json.is_array())
+            }
+            _ => None,
+        };
+        json.and_then(|json| serde_json::to_string_pretty(&json).ok())
+            .unwrap_or(value)
+    }
+}
+
+/// Short form of a data type: nested types show their children instead of
+/// their fields debug output
+pub fn type_name(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Struct(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|f| format!("{}: {}", f.name(), type_name(f.data_type())))
+                .collect();
+            format!("Struct<{}>", fields.join(", "))
+        }
+        DataType::List(field) | DataType::LargeList(field) => {
+            format!("List<{}>", type_name(field.data_type()))
+        }
+        DataType::FixedSizeList(field, size) => {
+            format!("List<{}; {}>", type_name(field.data_type()), size)
+        }
+        DataType::Map(field, _) => match field.data_type() {
+            DataType::Struct(entries) if entries.len() == 2 => format!(
+                "Map<{}, {}>",
+                type_name(entries[0].data_type()),
+                type_name(entries[1].data_type())
+            ),
+            _ => "Map".to_string(),
+        },
+        data_type => data_type.to_string(),
+    }
+}
+
+pub fn cell_value(column: &ArrayRef, index: usize) -> Option<String> {
+    if column
+        .logical_nulls()
+        .is_some_and(|nulls| nulls.is_null(index))
+    {
+        return None;
+    }
+    Some(array_value_to_string(column, index).unwrap_or_else(|e| e.to_string()))
+}
+
+/// Nested value written by the arrow json writer
+fn nested_to_json(column: &ArrayRef, index: usize) -> Option<serde_json::Value> {
+    let field = Field::new("value", column.data_type().clone(), true);
+    let batch = RecordBatch::try_new(
+        Arc::new(Schema::new(vec![field])),
+        vec![column.slice(index, 1)],
+    )
+    .ok()?;
+    let mut writer = ArrayWriter::new(Vec::new());
+    writer.write(&batch).ok()?;
+    writer.finish().ok()?;
+    let mut rows: serde_json::Value = serde_json::from_slice(&writer.into_inner()).ok()?;
+    Some(rows.get_mut(0)?.get_mut("value")?.take())
+}
+
+fn single_line(value: Option<&str>) -> String {
+    value.unwrap_or("null").replace(['\n', '\r', '\t'], " ")
+}
+
+/// Cut `text` to `width` columns, ending with … when truncated
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        used += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// Cells selected from an anchor to the cursor
+#[derive(Clone, Copy)]
+pub enum Selection {
+    /// rectangle of cells, anchored at a row and column
+    Cells(usize, usize),
+    /// whole rows, anchored at a row
+    Rows(usize),
+    /// whole columns, anchored at a column
+    Columns(usize),
+}
+
+/// Received rows of the cursor and of the selection anchor, to follow them
+/// once the shown rows moved
+#[derive(Default)]
+pub struct Anchors {
+    cursor: Option<usize>,
+    selection: Option<usize>,
+}
+
+/// Cursor and scroll position in a grid
+#[derive(Default)]
+pub struct GridView {
+    pub row: usize,
+    pub col: usize,
+    /// first columns kept on screen when scrolling horizontally
+    pub frozen: usize,
+    pub selection: Option<Selection>,
+    row_offset: usize,
+    col_offset: usize,
+    /// rows shown by the last render
+    page: usize,
+}
+
+impl GridView {
+    /// Move the cursor, staying within the grid
+    pub fn move_by(&mut self, grid: &Grid, rows: isize, cols: isize) {
+        self.row = self
+            .row
+            .saturating_add_signed(rows)
+            .min(grid.num_rows().saturating_sub(1));
+        self.col = self
+            .col
+            .saturating_add_signed(cols)
+            .min(grid.num_columns().saturating_sub(1));
+    }
+
+    pub fn anchors(&self, grid: &Grid) -> Anchors {
+        let selection = match self.selection {
+            Some(Selection::Cells(row, _) | Selection::Rows(row)) => grid.record(row),
+            _ => None,
+        };
+        Anchors {
+            cursor: grid.record(self.row),
+            selection,
+        }
+    }
+
+    /// Move the cursor and the selection anchor back to their rows
+    pub fn follow(&mut self, grid: &Grid, anchors: Anchors) {
+        if let Some(row) = anchors.cursor.and_then(|record| grid.shown_row(record)) {
+            self.row = row;
+        }
+        let anchor = anchors.selection.and_then(|record| grid.shown_row(record));
+        if let (Some(Selection::Cells(row, _) | Selection::Rows(row)), Some(anchor)) =
+            (self.selection.as_mut(), anchor)
+        {
+            *row = anchor;
+        }
+    }
+
+    pub fn page(&self) -> isize {
+        self.page.max(1) as isize
+    }
+
+    /// Start a selection anchored at the cursor, or stop it when it is of the
+    /// same kind
+    pub fn toggle_selection(&mut self, selection: Selection) {
+        self.selection = match self.selection {
+            Some(current) if discriminant(&current) == discriminant(&selection) => None,
+            _ => Some(selection),
+        };
+    }
+
+    /// Rows and columns of the selected cells, within the grid
+    pub fn selected(&self, grid: &Grid) -> Option<(Range<usize>, Range<usize>)> {
+        let span = |anchor: usize, cursor: usize, len: usize| {
+            let end = (anchor.max(cursor) + 1).min(len);
+            anchor.min(cursor).min(end)..end
+        };
+        let (rows, cols) = (grid.num_rows(), grid.num_columns());
+        match self.selection? {
+            Selection::Cells(row, col) => {
+                Some((span(row, self.row, rows), span(col, self.col, cols)))
+            }
+            Selection::Rows(row) => Some((span(row, self.row, rows), 0..cols)),
+            Selection::Columns(col) => Some((0..rows, span(col, self.col, cols))),
+        }
+    }
+
+    /// Freeze the columns up to the cursor one, or unfreeze them
+    pub fn toggle_freeze(&mut self) {
+        self.frozen = if self.frozen == self.col + 1 {
+            0
+        } else {
+            self.col + 1
+        };
+    }
+
+    /// Frozen columns then the scrolled ones fitting in `width` along with
+    /// their width, the cursor column being one of them and the last one
+    /// being clipped to the remaining space
+    fn visible_columns(&mut self, widths: &[usize], width: usize) -> Vec<(usize, usize)> {
+        let frozen = self.frozen.min(widths.len());
+        let mut columns = Vec::new();
+        let mut used = 0;
+        for (col, col_width) in widths.iter().copied().enumerate().take(frozen) {
+            if !columns.is_empty() && used + col_width > width {
+                break;
+            }
+            columns.push((col, col_width));
+            used += col_width + 1;
+        }
+        let remaining = width.saturating_sub(used);
+        self.col_offset = self.col_offset.max(frozen);
+        if self.col >= frozen {
+            self.col_offset = self.col_offset.min(self.col);
+            let span =
+                |from: usize, to: usize| -> usize { (from..=to).map(|col| widths[col] + 1).sum() };
+            while self.col_offset < self.col && span(self.col_offset, self.col) > remaining {
+                self.col_offset += 1;
+            }
+        }
+        let mut scrolled_width = 0;
+        for (col, col_width) in widths.iter().copied().enumerate().skip(self.col_offset) {
+            let first = scrolled_width == 0 && (used == 0 || col == self.col);
+            let left = remaining.saturating_sub(scrolled_width);
+            if col_width > left && !first {
+                // clipped, unless too narrow to show anything useful
+                if left >= 4 {
+                    columns.push((col, left));
+                }
+                break;
+            }
+            columns.push((col, col_width));
+            scrolled_width += col_width + 1;
+        }
+        columns
+    }
+
+    /// Header row and the rows around the cursor, the cells matching the
+    /// search being highlighted
+    pub fn render(&mut self, f: &mut Frame, area: Rect, grid: &Grid, search: Option<&Search>) {
+        self.page = area.height.saturating_sub(1) as usize;
+        if self.row < self.row_offset {
+            self.row_offset = self.row;
+        } else if self.row >= self.row_offset + self.page.max(1) {
+            self.row_offset = self.row + 1 - self.page.max(1);
+        }
+        let last_row = (self.row_offset + self.page).min(grid.num_rows());
+        let widths = grid.column_widths(self.row_offset..last_row);
+        let columns = self.visible_columns(&widths, area.width as usize);
+        let selected = self.selected(grid);
+
+        let header_style = Style::new().bold().fg(Color::Yellow);
+        let header = Row::new(columns.iter().map(|(col, width)| {
+            let name = truncate(&grid.header(*col), *width);
+            let style = if *col == self.col {
+                header_style.reversed()
+            } else if *col < self.frozen {
+                header_style.fg(Color::Cyan)
+            } else {
+                header_style
+            };
+            Cell::from(name).style(style)
+        }));
+        let rows = (self.row_offset..last_row).map(|row| {
+            let cells = columns.iter().map(|(col, width)| {
+                let value = grid.value(row, *col);
+                let text = truncate(&single_line(value.as_deref()), *width);
+                let style = match value {
+                    None => Style::new().italic().dim(),
+                    Some(_) if search.is_some_and(|s| s.is_match(row, *col)) => {
+                        Style::new().black().on_yellow()
+                    }
+                    Some(_) => Style::new(),
+                };
+                let style = match &selected {
+                    Some((rows, cols)) if rows.contains(&row) && cols.contains(col) => {
+                        style.on_blue()
+                    }
+                    _ => style,
+                };
+                let style = if row == self.row && *col == self.col {
+                    style.reversed()
+                } else {
+                    style
+                };
+                Cell::from(text).style(style)
+            });
+            let row_style = if row == self.row {
+                Style::new().bg(Color::DarkGray)
+            } else {
+                Style::new()
+            };
+            Row::new(cells).style(row_style)
+        });
+        let widths = columns
+            .iter()
+            .map(|(_, width)| Constraint::Length(*width as u16));
+        let table = Table::new(rows, widths)
+            .header(header)
+            .column_spacing(1)
+            .flex(Flex::Start);
+        f.render_widget(table, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int64Array, StringArray};
+
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, true),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("group", DataType::Utf8, true),
+        ]))
+    }
+
+    fn batch(ids: &[Option<i64>]) -> RecordBatch {
+        let names: Vec<String> = ids
+            .iter()
+            .map(|id| format!("n{}", id.unwrap_or_default()))
+            .collect();
+        let groups: Vec<&str> = ids
+            .iter()
+            .map(|id| {
+                if id.unwrap_or_default() % 2 == 0 {
+                    "even"
+                } else {
+                    "odd"
+                }
+            })
+            .collect();
+        RecordBatch::try_new(
+            schema(),
+            vec![
+                Arc::new(Int64Array::from(ids.to_vec())),
+                Arc::new(StringArray::from(names)),
+                Arc::new(StringArray::from(groups)),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn shown(grid: &Grid, col: usize) -> Vec<Option<String>> {
+        (0..grid.num_rows())
+            .map(|row| grid.value(row, col))
+            .collect()
+    }
+
+    fn ids(values: &[&str]) -> Vec<Option<String>> {
+        values.iter().map(|v| Some(v.to_string())).collect()
+    }
+
+    #[test]
+    fn push_merges_rows_in_sort_order() {
+        let mut grid = Grid::new(schema());
+        grid.push(batch(&[Some(5), Some(1), Some(3)]));
+        grid.toggle_sort(0, false).unwrap();
+        grid.push(batch(&[Some(4), None, Some(0), Some(6)]));
+        grid.push(batch(&[Some(2)]));
+        let mut expected = ids(&["0", "1", "2", "3", "4", "5", "6"]);
+        expected.push(None);
+        assert_eq!(shown(&grid, 0), expected);
+        // descending, nulls still last
+        grid.toggle_sort(0, false).unwrap();
+        grid.push(batch(&[Some(7)]));
+        let mut expected = ids(&["7", "6", "5", "4", "3", "2", "1", "0"]);
+        expected.push(None);
+        assert_eq!(shown(&grid, 0), expected);
+    }
+
+    #[test]
+    fn unhide_restores_the_shown_index() {
+        let mut grid = Grid::new(schema());
+        grid.push(batch(&[Some(1)]));
+        // id name group -> name group id
+        let col = grid.move_column(0, 1);
+        grid.move_column(col, 1);
+        grid.hide(1);
+        assert_eq!(grid.hidden().collect::<Vec<_>>(), ["group"]);
+        assert_eq!(grid.unhide(), Some(1));
+        let names: Vec<&str> = (0..grid.num_columns())
+            .map(|col| grid.field(col).name().as_str())
+            .collect();
+        assert_eq!(names, ["name", "group", "id"]);
+        assert_eq!(grid.unhide(), None);
+    }
+
+    #[test]
+    fn toggle_sort_cycles_directions() {
+        let mut grid = Grid::new(schema());
+        grid.push(batch(&[Some(2), Some(3), Some(1), Some(4)]));
+        grid.toggle_sort(0, false).unwrap();
+        assert_eq!(shown(&grid, 0), ids(&["1", "2", "3", "4"]));
+        grid.toggle_sort(0, false).unwrap();
+        assert_eq!(shown(&grid, 0), ids(&["4", "3", "2", "1"]));
+        // back to the received order
+        grid.toggle_sort(0, false).unwrap();
+        assert_eq!(grid.sort_keys().count(), 0);
+        assert_eq!(shown(&grid, 0), ids(&["2", "3", "1", "4"]));
+    }
+
+    #[test]
+    fn toggle_sort_keeps_keys_when_multi() {
+        let mut grid = Grid::new(schema());
+        grid.push(batch(&[Some(2), Some(3), Some(1), Some(4)]));
+        // by group then id descending
+        grid.toggle_sort(2, false).unwrap();
+        grid.toggle_sort(0, true).unwrap();
+        grid.toggle_sort(0, true).unwrap();
+        assert_eq!(
+            grid.sort_keys().collect::<Vec<_>>(),
+            [("group", false), ("id", true)]
+        );
+        assert_eq!(shown(&grid, 0), ids(&["4", "2", "3", "1"]));
+        // a single key replaces the others, ties in the received order
+        grid.toggle_sort(2, false).unwrap();
+        assert_eq!(grid.sort_keys().collect::<Vec<_>>(), [("group", true)]);
+        assert_eq!(shown(&grid, 0), ids(&["3", "1", "2", "4"]));
+    }
+
+    #[test]
+    fn filter_keeps_matching_rows() {
+        let mut grid = Grid::new(schema());
+        grid.push(batch(&[Some(1), Some(2), Some(3), Some(4)]));
+        grid.set_filter(2, Some(Pattern::new("ODD", false).unwrap()));
+        assert_eq!(grid.num_rows(), 0);
+        grid.set_filter(2, Some(Pattern::new("odd", false).unwrap()));
+        assert_eq!(shown(&grid, 0), ids(&["1", "3"]));
+        // pushed rows are filtered too, nulls never match
+        grid.push(batch(&[Some(5), Some(6)]));
+        grid.push(batch(&[None]));
+        assert_eq!(shown(&grid, 0), ids(&["1", "3", "5"]));
+        assert_eq!(grid.total_rows(), 7);
+        // filters of several columns all apply
+        grid.set_filter(1, Some(Pattern::new("^n[1-3]$", true).unwrap()));
+        assert_eq!(shown(&grid, 0), ids(&["1", "3"]));
+        grid.set_filter(2, None);
+        assert_eq!(shown(&grid, 0), ids(&["1", "2", "3"]));
+        grid.set_filter(1, None);
+        assert_eq!(grid.num_rows(), 7);
+    }
+}
@@ -0,0 +1,222 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use log::warn;
+use ratatui::{prelude::*, widgets::*};
+
+use super::search::Pattern;
+
+/// Queries run from the viewer, one json string per line
+const HISTORY_FILE: &str = ".adt_queries";
+/// Queries kept in the history file, the older ones being dropped
+const MAX_HISTORY: usize = 1000;
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+/// Queries run in the previous sessions, the most recent last
+pub fn load_history() -> Vec<String> {
+    history_path().map_or_else(Vec::new, |path| read_history(&path))
+}
+
+fn read_history(path: &Path) -> Vec<String> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let mut queries: Vec<String> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    queries.dedup();
+    let skipped = queries.len().saturating_sub(MAX_HISTORY);
+    queries.split_off(skipped)
+}
+
+/// Add a query to the history file
+pub fn append_history(query: &str) {
+    let Some(path) = history_path() else {
+        return;
+    };
+    if let Err(err) = write_history(&path, query) {
+        warn!("Unable to save query history: {}", err);
+    }
+}
+
+/// Append `query` to the history file, which is rewritten with its last
+/// `MAX_HISTORY` lines once it holds twice as many
+fn write_history(path: &Path, query: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::Value::from(query))?;
+    drop(file);
+    let content = fs::read_to_string(path)?;
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.len() < 2 * MAX_HISTORY {
+        return Ok(());
+    }
+    // replaced at once, not to lose the history when interrupted
+    let tmp_path = path.with_extension("tmp");
+    let mut tmp = fs::File::create(&tmp_path)?;
+    for line in &lines[lines.len() - MAX_HISTORY..] {
+        writeln!(tmp, "{}", line)?;
+    }
+    drop(tmp);
+    fs::rename(tmp_path, path)
+}
+
+/// What a key pressed in the history picker asks for
+pub enum Action {
+    None,
+    Close,
+    /// edit the query
+    Recall(String),
+    /// run the query again, in a new tab or not
+    Run(String, bool),
+}
+
+/// Queries of the history matching the typed text, the most recent first
+pub struct Picker {
+    input: String,
+    /// indexes of the matching queries in the history
+    matches: Vec<usize>,
+    selected: usize,
+    offset: usize,
+}
+
+impl Picker {
+    pub fn new(history: &[String]) -> Self {
+        let mut picker = Self {
+            input: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            offset: 0,
+        };
+        picker.update(history);
+        picker
+    }
+
+    fn update(&mut self, history: &[String]) {
+        let pattern = Pattern::new(&self.input, false).ok();
+        self.matches = (0..history.len())
+            .rev()
+            .filter(|index| {
+                pattern
+                    .as_ref()
+                    .is_none_or(|p| p.is_match(&history[*index]))
+            })
+            .collect();
+        self.selected = 0;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, history: &[String]) -> Action {
+        let selected = self
+            .matches
+            .get(self.selected)
+            .map(|index| history[*index].clone());
+        let run = matches!(key.code, KeyCode::Enter)
+            && key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Esc => return Action::Close,
+            KeyCode::F(5) => return selected.map_or(Action::None, |q| Action::Run(q, false)),
+            KeyCode::F(6) => return selected.map_or(Action::None, |q| Action::Run(q, true)),
+            _ if run => return selected.map_or(Action::None, |q| Action::Run(q, false)),
+            KeyCode::Enter => return selected.map_or(Action::None, Action::Recall),
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => {
+                self.selected = (self.selected + 1).min(self.matches.len().saturating_sub(1))
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.update(history);
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.push(c);
+                self.update(history);
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// Typed text above the matching queries, one line each
+    pub fn render(&mut self, f: &mut Frame, area: Rect, history: &[String]) {
+        let title = format!(" History {}/{} ", self.matches.len(), history.len());
+        let block = Block::bordered().title(title);
+        let inner = block.inner(area);
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+        let [input_area, list_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).areas(inner);
+
+        let prompt = format!("search: {}", self.input);
+        f.set_cursor(input_area.x + prompt.chars().count() as u16, input_area.y);
+        f.render_widget(Paragraph::new(prompt), input_area);
+
+        let height = list_area.height.max(1) as usize;
+        self.offset = self
+            .offset
+            .min(self.selected)
+            .max((self.selected + 1).saturating_sub(height));
+        let lines: Vec<Line> = self
+            .matches
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(height)
+            .map(|(position, index)| {
+                let query = history[*index]
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let line = Line::raw(query);
+                if position == self.selected {
+                    line.reversed()
+                } else {
+                    line
+                }
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines), list_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_is_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(HISTORY_FILE);
+        for i in 0..2 * MAX_HISTORY - 1 {
+            write_history(&path, &format!("select {}", i)).unwrap();
+        }
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2 * MAX_HISTORY - 1);
+        write_history(&path, "select\n  \"last\"").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), MAX_HISTORY);
+
+        let queries = read_history(&path);
+        assert_eq!(queries.len(), MAX_HISTORY);
+        assert_eq!(queries[0], format!("select {}", MAX_HISTORY));
+        assert_eq!(queries[MAX_HISTORY - 1], "select\n  \"last\"");
+    }
+
+    #[test]
+    fn history_skips_repeats_and_invalid_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(HISTORY_FILE);
+        assert!(read_history(&path).is_empty());
+        fs::write(
+            &path,
+            "\"select 1\"\nnot json\n\"select 1\"\n\"select 2\"\n",
+        )
+        .unwrap();
+        assert_eq!(read_history(&path), ["select 1", "select 2"]);
+    }
+}
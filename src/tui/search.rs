@@ -0,0 +1,130 @@
+use regex::{Regex, RegexBuilder};
+
+use super::grid::Grid;
+
+/// Text looked for in the cells: a substring or a regex, matched case
+/// insensitively unless it has upper case letters
+pub struct Pattern {
+    input: String,
+    matcher: Matcher,
+}
+
+enum Matcher {
+    Text { text: String, ignore_case: bool },
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn new(input: &str, regex: bool) -> Result<Self, regex::Error> {
+        let ignore_case = !input.chars().any(char::is_uppercase);
+        let matcher = if regex {
+            let regex = RegexBuilder::new(input)
+                .case_insensitive(ignore_case)
+                .build()?;
+            Matcher::Regex(regex)
+        } else if ignore_case {
+            Matcher::Text {
+                text: input.to_lowercase(),
+                ignore_case,
+            }
+        } else {
+            Matcher::Text {
+                text: input.to_string(),
+                ignore_case,
+            }
+        };
+        Ok(Self {
+            input: input.to_string(),
+            matcher,
+        })
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        match &self.matcher {
+            Matcher::Text { text, ignore_case } if *ignore_case => {
+                value.to_lowercase().contains(text.as_str())
+            }
+            Matcher::Text { text, .. } => value.contains(text.as_str()),
+            Matcher::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+/// Cells matching a pattern, in row then column order
+pub struct Search {
+    pattern: Pattern,
+    matches: Vec<(usize, usize)>,
+    /// rows of the grid already looked at
+    scanned: usize,
+}
+
+impl Search {
+    pub fn new(pattern: Pattern) -> Self {
+        Self {
+            pattern,
+            matches: Vec::new(),
+            scanned: 0,
+        }
+    }
+
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// Look for matches in the rows shown since the last update
+    pub fn update(&mut self, grid: &Grid) {
+        for row in self.scanned..grid.num_rows() {
+            for col in 0..grid.num_columns() {
+                if grid
+                    .value(row, col)
+                    .is_some_and(|value| self.pattern.is_match(&value))
+                {
+                    self.matches.push((row, col));
+                }
+            }
+        }
+        self.scanned = grid.num_rows();
+    }
+
+    /// Forget the matches once the rows of the grid changed
+    pub fn reset(&mut self) {
+        self.matches.clear();
+        self.scanned = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn is_match(&self, row: usize, col: usize) -> bool {
+        self.matches.binary_search(&(row, col)).is_ok()
+    }
+
+    /// Index of the match at `cell`
+    pub fn position(&self, cell: (usize, usize)) -> Option<usize> {
+        self.matches.binary_search(&cell).ok()
+    }
+
+    /// First match after `cell`, wrapping to the first one
+    pub fn next(&self, cell: (usize, usize)) -> Option<(usize, usize)> {
+        let index = self.matches.partition_point(|m| *m <= cell);
+        self.matches
+            .get(index)
+            .or_else(|| self.matches.first())
+            .copied()
+    }
+
+    /// Last match before `cell`, wrapping to the last one
+    pub fn prev(&self, cell: (usize, usize)) -> Option<(usize, usize)> {
+        let index = self.matches.partition_point(|m| *m < cell);
+        index
+            .checked_sub(1)
+            .and_then(|index| self.matches.get(index))
+            .or_else(|| self.matches.last())
+            .copied()
+    }
+}
@@ -0,0 +1,309 @@
+use std::future::Future;
+
+use arrow::datatypes::SchemaRef;
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use datafusion::dataframe::DataFrame;
+use futures::StreamExt;
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use super::grid::{Anchors, Grid, GridView};
+use super::search::{Pattern, Search};
+use crate::error::{AdtError, Result};
+use crate::export::ExportOptions;
+
+/// Sent by a query run from the viewer
+enum QueryEvent {
+    Started(SchemaRef),
+    Batch(RecordBatch),
+    Failed(AdtError),
+}
+
+/// Query run from the viewer, cancelled when dropped
+struct Running {
+    events: mpsc::Receiver<QueryEvent>,
+    task: JoinHandle<()>,
+    /// whether its results replaced the shown ones
+    started: bool,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Run the planned query, sending the schema of its results then their
+/// batches until the receiver is dropped
+async fn run_query(
+    plan: impl Future<Output = Result<DataFrame>>,
+    sender: mpsc::Sender<QueryEvent>,
+) {
+    let stream = match plan.await {
+        Ok(df) => df.execute_stream().await.map_err(AdtError::from),
+        Err(err) => Err(err),
+    };
+    let mut stream = match stream {
+        Ok(stream) => stream,
+        Err(err) => {
+            let _ = sender.send(QueryEvent::Failed(err)).await;
+            return;
+        }
+    };
+    if sender
+        .send(QueryEvent::Started(stream.schema()))
+        .await
+        .is_err()
+    {
+        return;
+    }
+    while let Some(batch) = stream.next().await {
+        let event = match batch {
+            Ok(batch) => QueryEvent::Batch(batch),
+            Err(err) => QueryEvent::Failed(err.into()),
+        };
+        let failed = matches!(event, QueryEvent::Failed(_));
+        if sender.send(event).await.is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// Result set shown in a tab, with its cursor and search
+pub struct Tab {
+    pub title: String,
+    pub grid: Grid,
+    pub view: GridView,
+    pub search: Option<Search>,
+    /// record batches still to come, taken as the rows are scrolled to
+    pub receiver: Option<mpsc::Receiver<RecordBatch>>,
+    /// take the batches as they arrive instead
+    pub fetch_all: bool,
+    /// path and options the shown rows are saved with once every row is
+    /// received
+    pub save: Option<(String, ExportOptions)>,
+    /// query whose results replace the shown ones once it is planned
+    running: Option<Running>,
+}
+
+impl Tab {
+    pub fn new(title: &str, schema: SchemaRef) -> Self {
+        Self {
+            title: title.to_string(),
+            grid: Grid::new(schema),
+            view: GridView::default(),
+            search: None,
+            receiver: None,
+            fetch_all: false,
+            save: None,
+            running: None,
+        }
+    }
+
+    /// Run the query planned by `plan`, cancelling the running one
+    pub fn run(
+        &mut self,
+        title: &str,
+        plan: impl Future<Output = Result<DataFrame>> + Send + 'static,
+    ) {
+        let (sender, events) = mpsc::channel(2);
+        let task = tokio::spawn(run_query(plan, sender));
+        self.title = title.to_string();
+        self.running = Some(Running {
+            events,
+            task,
+            started: false,
+        });
+    }
+
+    /// Whether rows are still to come
+    pub fn is_loading(&self) -> bool {
+        self.receiver.is_some() || self.running.is_some()
+    }
+
+    /// Whether more rows are needed: the ones of the next pages below the
+    /// cursor, or all of them
+    fn wants_rows(&self) -> bool {
+        self.fetch_all || self.grid.num_rows() < self.view.row + 2 * self.view.page() as usize
+    }
+
+    /// Take the batches received since the last tick, as long as rows are
+    /// missing below the cursor. The others wait in the channel, pausing the
+    /// query until they are taken. Returns the error of the running query.
+    pub fn poll(&mut self) -> Option<AdtError> {
+        let mut error = None;
+        let mut anchors = self.view.anchors(&self.grid);
+        let mut moved = false;
+        while self.wants_rows() {
+            let Some(receiver) = self.receiver.as_mut() else {
+                break;
+            };
+            match receiver.try_recv() {
+                Ok(batch) => moved |= self.grid.push(batch),
+                Err(mpsc::error::TryRecvError::Empty) => break,
+                Err(mpsc::error::TryRecvError::Disconnected) => self.receiver = None,
+            }
+        }
+        loop {
+            match &self.running {
+                Some(running) if running.started && !self.wants_rows() => break,
+                Some(_) => {}
+                None => break,
+            }
+            let Some(running) = self.running.as_mut() else {
+                break;
+            };
+            match running.events.try_recv() {
+                Ok(QueryEvent::Started(schema)) => {
+                    running.started = true;
+                    self.grid = Grid::new(schema);
+                    self.view = GridView::default();
+                    // the previous results are not shown anymore
+                    self.receiver = None;
+                    self.fetch_all = false;
+                    self.save = None;
+                    anchors = Anchors::default();
+                    moved = false;
+                    if let Some(search) = self.search.as_mut() {
+                        search.reset();
+                    }
+                }
+                Ok(QueryEvent::Batch(batch)) => moved |= self.grid.push(batch),
+                Ok(QueryEvent::Failed(err)) => error = Some(err),
+                Err(mpsc::error::TryRecvError::Empty) => break,
+                Err(mpsc::error::TryRecvError::Disconnected) => self.running = None,
+            }
+        }
+        if moved {
+            // the matches and the cursor are at the rows shown before
+            self.view.follow(&self.grid, anchors);
+            self.grid_changed();
+        } else if let Some(search) = self.search.as_mut() {
+            search.update(&self.grid);
+        }
+        error
+    }
+
+    pub fn set_search(&mut self, pattern: Option<Pattern>) {
+        self.search = pattern.map(Search::new);
+        if let Some(search) = self.search.as_mut() {
+            search.update(&self.grid);
+        }
+    }
+
+    pub fn set_filter(&mut self, col: usize, pattern: Option<Pattern>) {
+        self.grid.set_filter(col, pattern);
+        self.grid_changed();
+    }
+
+    /// Look for the search matches again and keep the cursor within the grid
+    /// once its rows or columns changed
+    fn grid_changed(&mut self) {
+        if let Some(search) = self.search.as_mut() {
+            search.reset();
+            search.update(&self.grid);
+        }
+        self.view.move_by(&self.grid, 0, 0);
+    }
+
+    /// Sort by the cursor column, along with the other sort keys when `multi`
+    pub fn sort(&mut self, multi: bool) -> Result<(), ArrowError> {
+        if self.grid.num_columns() == 0 {
+            return Ok(());
+        }
+        let sorted = self.grid.toggle_sort(self.view.col, multi);
+        self.grid_changed();
+        sorted
+    }
+
+    pub fn hide_column(&mut self) {
+        self.grid.hide(self.view.col);
+        self.view.frozen = self.view.frozen.min(self.grid.num_columns());
+        self.grid_changed();
+    }
+
+    pub fn unhide_column(&mut self) {
+        if let Some(col) = self.grid.unhide() {
+            self.view.col = col;
+            self.grid_changed();
+        }
+    }
+
+    /// Move the cursor column left or right, the cursor following it
+    pub fn move_column(&mut self, offset: isize) {
+        self.view.col = self.grid.move_column(self.view.col, offset);
+        self.grid_changed();
+    }
+
+    /// Move the cursor to the next or previous match of the search, returns
+    /// whether there is one
+    pub fn jump(&mut self, forward: bool) -> bool {
+        let Some(search) = &self.search else {
+            return true;
+        };
+        let cell = (self.view.row, self.view.col);
+        let found = if forward {
+            search.next(cell)
+        } else {
+            search.prev(cell)
+        };
+        if let Some((row, col)) = found {
+            self.view.row = row;
+            self.view.col = col;
+        }
+        found.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::grid::Selection;
+    use arrow::array::{Int64Array, StringArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use std::sync::Arc;
+
+    fn batch(ids: &[i64]) -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, false),
+        ]);
+        let names: Vec<String> = ids.iter().map(|id| format!("n{}", id)).collect();
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int64Array::from(ids.to_vec())),
+                Arc::new(StringArray::from(names)),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn sorted_push_keeps_search_and_cursor_rows() {
+        let (sender, receiver) = mpsc::channel(4);
+        let mut tab = Tab::new("tbl", batch(&[]).schema());
+        tab.receiver = Some(receiver);
+        tab.fetch_all = true;
+        sender.try_send(batch(&[5, 1, 3])).unwrap();
+        tab.poll();
+        tab.sort(false).unwrap();
+        tab.set_search(Some(Pattern::new("n3|n2", true).unwrap()));
+        assert!(tab.search.as_ref().unwrap().is_match(1, 1));
+        // on id 5, selecting from id 3
+        tab.view.row = 1;
+        tab.view.selection = Some(Selection::Rows(1));
+        tab.view.row = 2;
+
+        sender.try_send(batch(&[4, 2])).unwrap();
+        tab.poll();
+        // 1 2 3 4 5
+        assert_eq!(tab.grid.value(tab.view.row, 0).as_deref(), Some("5"));
+        assert!(matches!(tab.view.selection, Some(Selection::Rows(2))));
+        let search = tab.search.as_ref().unwrap();
+        assert_eq!(search.len(), 2);
+        assert!(search.is_match(1, 1) && search.is_match(2, 1));
+        assert!(tab.jump(true));
+        assert_eq!(tab.grid.value(tab.view.row, 1).as_deref(), Some("n2"));
+    }
+}
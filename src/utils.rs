@@ -1,7 +1,11 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use datafusion::arrow::datatypes::{DataType, TimeUnit};
+use deltalake::DeltaVersion;
 use url::{ParseError, Url};
 
-pub fn type_from_str(type_str: &str) -> Result<DataType, String> {
+use crate::error::{AdtError, Result};
+
+pub fn type_from_str(type_str: &str) -> Result<DataType> {
     match type_str {
         "int" => Ok(DataType::Int32),
         "bigint" => Ok(DataType::Int64),
@@ -11,21 +15,147 @@ pub fn type_from_str(type_str: &str) -> Result<DataType, String> {
         "date" => Ok(DataType::Date32),
         "timestamp" => Ok(DataType::Timestamp(TimeUnit::Second, None)),
         "timestamp_ms" => Ok(DataType::Timestamp(TimeUnit::Millisecond, None)),
-        _ => Err(String::from("Unsupported type string")),
+        _ => Err(AdtError::UnsupportedType(type_str.to_string())),
     }
 }
 
-pub fn ensure_scheme(s: &str) -> Result<Url, ()> {
+pub fn ensure_scheme(s: &str) -> Result<Url> {
+    let invalid = |reason: String| AdtError::InvalidPath {
+        path: s.to_string(),
+        reason,
+    };
     match Url::parse(s) {
         Ok(url) => Ok(url),
         Err(ParseError::RelativeUrlWithoutBase) => {
-            let local_path = std::path::Path::new(s).canonicalize().unwrap();
-            if local_path.is_file() {
-                Url::from_file_path(&local_path)
-            } else {
+            // output paths may not exist yet
+            let path = std::path::Path::new(s);
+            let local_path = match path.canonicalize() {
+                Ok(local_path) => local_path,
+                Err(_) => std::env::current_dir()
+                    .map_err(|e| invalid(e.to_string()))?
+                    .join(path),
+            };
+            if local_path.is_dir() || s.ends_with('/') {
                 Url::from_directory_path(&local_path)
+            } else {
+                Url::from_file_path(&local_path)
             }
+            .map_err(|_| invalid("not a valid local path".to_string()))
+        }
+        Err(e) => Err(invalid(e.to_string())),
+    }
+}
+
+/// Parse a timestamp given either as RFC 3339 (`2024-01-31T12:00:00+01:00`),
+/// `YYYY-MM-DD HH:MM:SS` or `YYYY-MM-DD`. Naive timestamps are read as UTC.
+pub fn timestamp_from_str(ts_str: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(ts_str) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+    if let Ok(ts) = NaiveDateTime::parse_from_str(ts_str, "%Y-%m-%d %H:%M:%S") {
+        return Ok(ts.and_utc());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(ts_str, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    Err(format!(
+        "Invalid timestamp '{}', expected RFC 3339, 'YYYY-MM-DD HH:MM:SS' or 'YYYY-MM-DD'",
+        ts_str
+    ))
+}
+
+pub fn delta_version(version: Option<i64>, as_of: Option<DateTime<Utc>>) -> DeltaVersion {
+    match (version, as_of) {
+        (Some(v), _) => DeltaVersion::Version(v),
+        (None, Some(ts)) => DeltaVersion::Timestamp(ts),
+        (None, None) => DeltaVersion::Newest,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use datafusion::arrow::array::Int64Array;
+    use datafusion::arrow::datatypes::{Field, Schema};
+    use datafusion::arrow::record_batch::RecordBatch;
+    use deltalake::DeltaOps;
+    use std::sync::Arc;
+
+    use crate::table::{TableContext, TableSpec};
+
+    #[test]
+    fn timestamp_formats() {
+        let noon = Utc.with_ymd_and_hms(2024, 1, 31, 12, 0, 0).unwrap();
+        assert_eq!(timestamp_from_str("2024-01-31T13:00:00+01:00"), Ok(noon));
+        assert_eq!(timestamp_from_str("2024-01-31T12:00:00Z"), Ok(noon));
+        assert_eq!(timestamp_from_str("2024-01-31 12:00:00"), Ok(noon));
+        let midnight = Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap();
+        assert_eq!(timestamp_from_str("2024-01-31"), Ok(midnight));
+        for invalid in ["2024-01-31 12:00", "31/01/2024", "2024-02-30", "yesterday"] {
+            assert!(timestamp_from_str(invalid).is_err(), "{}", invalid);
         }
-        Err(_) => Err(()),
+    }
+
+    #[test]
+    fn version_before_timestamp() {
+        let ts = Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap();
+        assert_eq!(delta_version(Some(3), Some(ts)), DeltaVersion::Version(3));
+        assert_eq!(delta_version(None, Some(ts)), DeltaVersion::Timestamp(ts));
+        assert_eq!(delta_version(None, None), DeltaVersion::Newest);
+    }
+
+    /// Delta table of two versions, of 1 then 3 rows
+    async fn delta_table() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        for ids in [vec![1], vec![2, 3]] {
+            let batch = RecordBatch::try_new(schema.clone(), vec![Arc::new(Int64Array::from(ids))])
+                .unwrap();
+            DeltaOps::try_from_uri(dir.path().to_str().unwrap())
+                .await
+                .unwrap()
+                .write(vec![batch])
+                .await
+                .unwrap();
+        }
+        dir
+    }
+
+    async fn count_rows(path: &str, version: DeltaVersion) -> Result<usize> {
+        let spec = TableSpec::new("tbl", path)?.with_version(version);
+        let tblctx = TableContext::new(vec![spec]);
+        tblctx.register_tables().await?;
+        Ok(tblctx
+            .context()
+            .sql("select * from tbl")
+            .await?
+            .count()
+            .await?)
+    }
+
+    #[tokio::test]
+    async fn time_travel() {
+        let dir = delta_table().await;
+        let path = dir.path().to_str().unwrap();
+        assert_eq!(count_rows(path, DeltaVersion::Newest).await.unwrap(), 3);
+        assert_eq!(count_rows(path, DeltaVersion::Version(0)).await.unwrap(), 1);
+        let now = DeltaVersion::Timestamp(Utc::now() + chrono::Duration::minutes(1));
+        assert_eq!(count_rows(path, now).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn time_travel_out_of_the_log() {
+        let dir = delta_table().await;
+        let path = dir.path().to_str().unwrap();
+        let err = count_rows(path, DeltaVersion::Version(5))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AdtError::DeltaLog(_)), "{}", err);
+        let before = Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap();
+        let err = count_rows(path, DeltaVersion::Timestamp(before))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AdtError::DeltaLog(_)), "{}", err);
     }
 }
@@ -0,0 +1,308 @@
+use std::io::Write;
+use std::time::Instant;
+
+use arrow::record_batch::RecordBatch;
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::execution::SendableRecordBatchStream;
+use futures::StreamExt;
+use log::info;
+use tokio::sync::mpsc;
+use unicode_width::UnicodeWidthStr;
+
+use crate::error::{AdtError, Result};
+use crate::export::StreamExport;
+use crate::tui::{truncate, WIDTH_SAMPLE_ROWS};
+
+/// Where the query results are shown
+pub enum Display {
+    /// printed on stdout as they arrive
+    Print,
+    /// sent to the TUI viewer, until it is closed
+    Viewer(mpsc::Sender<RecordBatch>),
+}
+
+/// Prints record batches as a single table. As the results are not held in
+/// memory, column widths are the ones of the first `WIDTH_SAMPLE_ROWS` rows,
+/// printed once received, and the longer values of the next rows are
+/// truncated.
+struct TablePrinter<W: Write> {
+    schema: SchemaRef,
+    out: W,
+    /// first rows, until the widths are known
+    sample: Vec<Vec<String>>,
+    widths: Option<Vec<usize>>,
+}
+
+impl<W: Write> TablePrinter<W> {
+    fn new(schema: SchemaRef, out: W) -> Self {
+        Self {
+            schema,
+            out,
+            sample: Vec::new(),
+            widths: None,
+        }
+    }
+
+    fn print(&mut self, batch: &RecordBatch) -> Result<()> {
+        let rows = format_rows(batch)?;
+        match &self.widths {
+            Some(widths) => {
+                for row in rows {
+                    writeln!(self.out, "{}", table_line(&row, widths))?;
+                }
+            }
+            None => {
+                self.sample.extend(rows);
+                if self.sample.len() >= WIDTH_SAMPLE_ROWS {
+                    self.print_sample()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Print the header and the sampled rows, with the widths of their values
+    fn print_sample(&mut self) -> Result<()> {
+        let names: Vec<String> = self
+            .schema
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect();
+        let mut widths: Vec<usize> = names.iter().map(|name| name.width()).collect();
+        for row in self.sample.iter() {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = (*width).max(value.width());
+            }
+        }
+        let border = table_border(&widths);
+        writeln!(self.out, "{}", border)?;
+        writeln!(self.out, "{}", table_line(&names, &widths))?;
+        writeln!(self.out, "{}", border)?;
+        for row in self.sample.drain(..) {
+            writeln!(self.out, "{}", table_line(&row, &widths))?;
+        }
+        self.widths = Some(widths);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        if self.widths.is_none() {
+            self.print_sample()?;
+        }
+        writeln!(
+            self.out,
+            "{}",
+            table_border(self.widths.as_deref().unwrap_or_default())
+        )?;
+        Ok(())
+    }
+}
+
+/// Values of each row on a single line, nulls being empty
+fn format_rows(batch: &RecordBatch) -> Result<Vec<Vec<String>>> {
+    let options = FormatOptions::default();
+    let formatters = batch
+        .columns()
+        .iter()
+        .map(|column| ArrayFormatter::try_new(column.as_ref(), &options))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((0..batch.num_rows())
+        .map(|row| {
+            formatters
+                .iter()
+                .map(|formatter| {
+                    formatter
+                        .value(row)
+                        .to_string()
+                        .replace(['\n', '\r', '\t'], " ")
+                })
+                .collect()
+        })
+        .collect())
+}
+
+fn table_border(widths: &[usize]) -> String {
+    let mut border = String::from("+");
+    for width in widths {
+        border.push_str(&"-".repeat(width + 2));
+        border.push('+');
+    }
+    border
+}
+
+fn table_line(values: &[String], widths: &[usize]) -> String {
+    let mut line = String::from("|");
+    for (value, width) in values.iter().zip(widths) {
+        let value = truncate(value, *width);
+        line.push(' ');
+        line.push_str(&value);
+        line.push_str(&" ".repeat(width - value.width()));
+        line.push_str(" |");
+    }
+    line
+}
+
+/// Execute the query stream once, showing its batches while they are
+/// exported. Stops early when the viewer is closed and there is no export,
+/// even while a batch is being computed.
+pub async fn drain_results(
+    mut stream: SendableRecordBatchStream,
+    display: Display,
+    export: Option<StreamExport>,
+) -> Result<()> {
+    let mut printer = match display {
+        Display::Print => Some(TablePrinter::new(stream.schema(), std::io::stdout())),
+        Display::Viewer(_) => None,
+    };
+    let mut viewer = match display {
+        Display::Viewer(sender) => Some(sender),
+        Display::Print => None,
+    };
+    let req_time = Instant::now();
+    loop {
+        let batch = match (&viewer, &export) {
+            (Some(sender), None) => tokio::select! {
+                batch = stream.next() => batch,
+                _ = sender.closed() => return Ok(()),
+            },
+            _ => stream.next().await,
+        };
+        let Some(batch) = batch else {
+            break;
+        };
+        let batch = match batch {
+            Ok(batch) => batch,
+            Err(err) => {
+                if let Some(export) = export {
+                    export.abort().await;
+                }
+                return Err(AdtError::from(err));
+            }
+        };
+        if let Some(printer) = printer.as_mut() {
+            printer.print(&batch)?;
+        }
+        if let Some(sender) = &viewer {
+            if sender.send(batch.clone()).await.is_err() {
+                viewer = None;
+                if export.is_none() {
+                    return Ok(());
+                }
+            }
+        }
+        if let Some(export) = &export {
+            export.send(batch).await?;
+        }
+    }
+    info!("Query execution time: {:.2?}", req_time.elapsed());
+    if let Some(printer) = printer {
+        printer.finish()?;
+    }
+    match export {
+        Some(export) => export.finish().await,
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int64Array, StringArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+    use std::sync::Arc;
+
+    fn batch(ids: Vec<i64>, names: Vec<Option<&str>>) -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]);
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int64Array::from(ids)),
+                Arc::new(StringArray::from(names)),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn printed(batches: &[RecordBatch]) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut printer = TablePrinter::new(batches[0].schema(), &mut out);
+        for batch in batches {
+            printer.print(batch).unwrap();
+        }
+        printer.finish().unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn print_batches_as_one_table() {
+        let lines = printed(&[
+            batch(vec![1, 2], vec![Some("a"), None]),
+            batch(vec![10], vec![Some("multi\nline")]),
+        ]);
+        let expected = [
+            "+----+------------+",
+            "| id | name       |",
+            "+----+------------+",
+            "| 1  | a          |",
+            "| 2  |            |",
+            "| 10 | multi line |",
+            "+----+------------+",
+        ];
+        assert_eq!(lines, expected);
+    }
+
+    #[test]
+    fn print_empty_results() {
+        let lines = printed(&[batch(vec![], vec![])]);
+        assert_eq!(
+            lines,
+            [
+                "+----+------+",
+                "| id | name |",
+                "+----+------+",
+                "+----+------+"
+            ]
+        );
+    }
+
+    #[test]
+    fn truncate_values_after_the_sample() {
+        let ids = (0..WIDTH_SAMPLE_ROWS as i64).collect::<Vec<_>>();
+        let names = vec![Some("ab"); WIDTH_SAMPLE_ROWS];
+        let lines = printed(&[batch(ids, names), batch(vec![1], vec![Some("abcdef")])]);
+        assert_eq!(lines.len(), WIDTH_SAMPLE_ROWS + 5);
+        assert_eq!(lines[3], "| 0   | ab   |");
+        assert_eq!(lines[WIDTH_SAMPLE_ROWS + 3], "| 1   | abc… |");
+    }
+
+    #[tokio::test]
+    async fn drain_results_to_the_viewer() {
+        let batches = vec![
+            Ok(batch(vec![1], vec![Some("a")])),
+            Ok(batch(vec![2], vec![Some("b")])),
+        ];
+        let stream = Box::pin(RecordBatchStreamAdapter::new(
+            batches[0].as_ref().unwrap().schema(),
+            futures::stream::iter(batches),
+        ));
+        let (sender, mut receiver) = mpsc::channel(2);
+        drain_results(stream, Display::Viewer(sender), None)
+            .await
+            .unwrap();
+        let mut rows = 0;
+        while let Some(batch) = receiver.recv().await {
+            rows += batch.num_rows();
+        }
+        assert_eq!(rows, 2);
+    }
+}